@@ -1,21 +1,215 @@
 mod utils;
 
+use clap::Parser;
+
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
 use utils::{
-    argument_handler::argument_handler, get_args_type, plot_handler::plot_handler, Arguments,
+    cli::{describe_waveform, list_signals, Cli, Command},
+    csv::CsvExport,
+    loader::Loader,
+    plot_handler::plot_handler,
+    render::{buffer_to_text, render_view},
+    ruler::parse_time,
+    svg::SvgPlot,
+    waveform::Waveform,
+    wavejson::{self, WaveJson},
 };
 
-fn main() {
-    let args_type = get_args_type();
-
-    if args_type == Arguments::Version
-        || args_type == Arguments::Empty
-        || args_type == Arguments::Help
-        || args_type == Arguments::Version
-    {
-        // Handle any argument that doesn't require plotting
-       let _ = argument_handler();
-    } else {
-        // Handle vcd files and plot
-       let _ = plot_handler();
+// How long the viewer waits for a file to be read before showing what it has
+const STARTUP_WAIT: Duration = Duration::from_millis(500);
+
+fn main() -> ExitCode {
+    // Usage errors are reported by clap, which exits with status 2
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("waveplot: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Some(Command::Svg {
+            file,
+            view,
+            output,
+            width,
+        }) => {
+            let waveform = Waveform::load(file)?;
+            let view = view.resolve(&waveform)?;
+            let svg = SvgPlot::new(&waveform, &view.signals, view.start, view.end)
+                .radix(view.radix)
+                .cursor(view.cursor)
+                .markers(&view.markers.markers)
+                .width(width)
+                .render();
+            write_output(output, &svg)?;
+        }
+        Some(Command::Wavejson {
+            file,
+            view,
+            output,
+            period,
+        }) => {
+            let waveform = Waveform::load(file)?;
+            let view = view.resolve(&waveform)?;
+            let period = period
+                .map(|period| parse_time(&period, waveform.timescale))
+                .transpose()?;
+            let json = WaveJson::new(&waveform, &view.signals, view.start, view.end)
+                .radix(view.radix)
+                .period(period)
+                .render();
+            write_output(output, &json)?;
+        }
+        Some(Command::Csv {
+            file,
+            view,
+            output,
+            mode,
+            period,
+        }) => {
+            let waveform = Waveform::load(file)?;
+            let view = view.resolve(&waveform)?;
+            let period = period
+                .map(|period| parse_time(&period, waveform.timescale))
+                .transpose()?;
+            let csv = CsvExport::new(&waveform, &view.signals, view.start, view.end)
+                .radix(view.radix)
+                .mode(mode)
+                .period(period)
+                .render();
+            write_output(output, &csv)?;
+        }
+        Some(Command::Info { file }) => {
+            writeln!(
+                io::stdout(),
+                "{}",
+                describe_waveform(&Waveform::load(file)?)
+            )?;
+        }
+        Some(Command::Render {
+            file,
+            view,
+            width,
+            signal_height,
+            color,
+            ascii,
+        }) => {
+            let waveform = Waveform::load(file)?;
+            let view = view.resolve(&waveform)?;
+            let text: Vec<String> = render_view(&waveform, &view, width, signal_height)
+                .iter()
+                .map(|buffer| buffer_to_text(buffer, color.enabled(), ascii))
+                .collect();
+            writeln!(io::stdout(), "{}", text.join("\n"))?;
+        }
+        Some(Command::List { file, pattern }) => {
+            let waveform = Waveform::load(file)?;
+            writeln!(
+                io::stdout(),
+                "{}",
+                list_signals(&waveform, pattern.as_deref())
+            )?;
+        }
+        None => {
+            // clap requires the file when no subcommand is given
+            let path = cli.file.unwrap_or_default();
+            // WaveJSON and FST files are read whole
+            let whole = path
+                .extension()
+                .is_some_and(|extension| extension == "json" || extension == "fst");
+            if cli.follow && (whole || path == Path::new("-")) {
+                return Err(
+                    format!("{}: only plain VCD files can be followed", path.display()).into(),
+                );
+            }
+            let (overlay, overlay_period) = (cli.overlay, cli.overlay_period);
+            if whole {
+                let mut waveform = Waveform::load(path)?;
+                let overlaid = add_overlay(&mut waveform, overlay, overlay_period)?;
+                let mut view = cli.view.resolve(&waveform)?;
+                if cli.view.signals.is_empty() {
+                    view.signals.extend(overlaid);
+                }
+                return plot_handler(waveform, view, None);
+            }
+
+            // Large files are browsed while the rest of them is read, and files being
+            // written while they grow
+            let (mut waveform, mut loader) = if cli.follow {
+                Loader::follow(&path)?
+            } else {
+                Loader::open(&path)?
+            };
+            let overlaid = add_overlay(&mut waveform, overlay, overlay_period)?;
+
+            // The window asked for may start past what has been read so far
+            let start = cli
+                .view
+                .start
+                .as_deref()
+                .map_or(Ok(0), |time| parse_time(time, waveform.timescale))?;
+            loader.wait(&mut waveform, STARTUP_WAIT);
+            while !loader.done && waveform.last_timestamp() <= start {
+                loader.wait(&mut waveform, STARTUP_WAIT);
+            }
+            let mut view = match cli.view.resolve(&waveform) {
+                Ok(view) => view,
+                // Reading may have stopped at an error before the window
+                Err(err) => return Err(loader.error.take().map_or(err, Into::into)),
+            };
+            if cli.view.signals.is_empty() {
+                view.signals.extend(overlaid);
+            }
+            plot_handler(waveform, view, Some(loader))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Add the signals of the WaveJSON diagram at `path` to `waveform`, under a scope
+// named after the file, and return them to be plotted after those of the dump
+fn add_overlay(
+    waveform: &mut Waveform,
+    path: Option<PathBuf>,
+    period: Option<String>,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let Some(path) = path else {
+        return Ok(Vec::new());
+    };
+    let in_path = |err: &dyn Error| format!("{}: {}", path.display(), err);
+
+    let text = fs::read_to_string(&path).map_err(|err| in_path(&err))?;
+    let period = period
+        .map(|period| parse_time(&period, waveform.timescale))
+        .transpose()?;
+    let name = path.file_stem().map_or(String::from("overlay"), |stem| {
+        stem.to_string_lossy().into_owned()
+    });
+    Ok(wavejson::overlay(waveform, &text, &name, period).map_err(|err| in_path(&*err))?)
+}
+
+// Write an export to `output`, or to stdout without one
+fn write_output(output: Option<PathBuf>, text: &str) -> Result<(), Box<dyn Error>> {
+    match output {
+        Some(path) => {
+            fs::write(&path, text).map_err(|err| format!("{}: {}", path.display(), err))?
+        }
+        None => io::stdout().write_all(text.as_bytes())?,
     }
+    Ok(())
 }
@@ -0,0 +1,386 @@
+use clap::{ArgAction, Args, Parser, Subcommand};
+
+use std::{error::Error, path::PathBuf};
+
+use super::{
+    csv::CsvMode,
+    markers::Markers,
+    radix::Radix,
+    render::ColorMode,
+    ruler::{format_time, parse_time},
+    search::glob_match,
+    theme::Theme,
+    waveform::Waveform,
+};
+
+// Command line of waveplot: browse a VCD or FST file, or run one of the subcommands
+#[derive(Parser)]
+#[command(
+    name = "waveplot",
+    version,
+    about = "A VCD and FST waveform viewer for the terminal",
+    disable_version_flag = true,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[arg(
+        short = 'V',
+        long,
+        visible_short_alias = 'v',
+        action = ArgAction::Version,
+        help = "Print version"
+    )]
+    version: Option<bool>,
+
+    #[arg(required = true, help = "VCD or FST file to browse, - for stdin")]
+    pub file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub view: ViewOptions,
+
+    #[arg(
+        short,
+        long,
+        help = "Keep reading the file as a running simulation appends to it (plain VCD files only)"
+    )]
+    pub follow: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "WaveJSON diagram to show next to the file, under a scope named after it"
+    )]
+    pub overlay: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "TIME",
+        requires = "overlay",
+        help = "Time per period of the overlaid diagram [default: from its foot]"
+    )]
+    pub overlay_period: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Print signals as text, without taking over the terminal")]
+    Render {
+        #[arg(help = "VCD or FST file to render, - for stdin")]
+        file: PathBuf,
+        #[command(flatten)]
+        view: ViewOptions,
+        #[arg(
+            short,
+            long,
+            default_value_t = 120,
+            value_parser = clap::value_parser!(u16).range(20..=4096),
+            help = "Width of the output in columns"
+        )]
+        width: u16,
+        #[arg(
+            long,
+            default_value_t = 2,
+            value_parser = clap::value_parser!(u16).range(1..=8),
+            help = "Rows per signal"
+        )]
+        signal_height: u16,
+        #[arg(
+            long,
+            default_value = "auto",
+            help = "Color the output with ANSI escape codes"
+        )]
+        color: ColorMode,
+        #[arg(long, help = "Draw with ASCII characters only")]
+        ascii: bool,
+    },
+    #[command(about = "Draw signals with their names, values and a time ruler as SVG")]
+    Svg {
+        #[arg(help = "VCD or FST file to draw, - for stdin")]
+        file: PathBuf,
+        #[command(flatten)]
+        view: ViewOptions,
+        #[arg(short, long, help = "File to write [default: stdout]")]
+        output: Option<PathBuf>,
+        #[arg(
+            short,
+            long,
+            default_value_t = 1200,
+            value_parser = clap::value_parser!(u32).range(200..),
+            help = "Width of the drawing in pixels"
+        )]
+        width: u32,
+    },
+    #[command(
+        about = "Convert signals to a WaveDrom (WaveJSON) timing diagram",
+        long_about = "Convert signals to a WaveDrom (WaveJSON) timing diagram. \
+            Diagrams are opened on their own with `waveplot diagram.json`, \
+            or next to a dump with `waveplot dump.vcd --overlay diagram.json`"
+    )]
+    Wavejson {
+        #[arg(help = "VCD or FST file to convert, - for stdin")]
+        file: PathBuf,
+        #[command(flatten)]
+        view: ViewOptions,
+        #[arg(short, long, help = "File to write [default: stdout]")]
+        output: Option<PathBuf>,
+        #[arg(
+            short,
+            long,
+            value_name = "TIME",
+            help = "Time per character of the waves [default: fit every change]"
+        )]
+        period: Option<String>,
+    },
+    #[command(about = "Write the values of signals as CSV")]
+    Csv {
+        #[arg(help = "VCD or FST file to export, - for stdin")]
+        file: PathBuf,
+        #[command(flatten)]
+        view: ViewOptions,
+        #[arg(short, long, help = "File to write [default: stdout]")]
+        output: Option<PathBuf>,
+        #[arg(
+            long,
+            default_value = "samples",
+            help = "A row per timestamp with a column per signal, or a row per change"
+        )]
+        mode: CsvMode,
+        #[arg(
+            short,
+            long,
+            value_name = "TIME",
+            help = "Time between samples [default: every change]"
+        )]
+        period: Option<String>,
+    },
+    #[command(about = "Print the header and size of a VCD or FST file")]
+    Info {
+        #[arg(help = "VCD or FST file to describe, - for stdin")]
+        file: PathBuf,
+    },
+    #[command(about = "Print the path, width and type of every signal")]
+    List {
+        #[arg(help = "VCD or FST file to list, - for stdin")]
+        file: PathBuf,
+        #[arg(help = "Only list signals whose path matches, with * and ? wildcards")]
+        pattern: Option<String>,
+    },
+}
+
+// What is shown first: which signals, over which time window, and how
+#[derive(Args, Clone, Debug)]
+pub struct ViewOptions {
+    #[arg(
+        short,
+        long,
+        value_name = "NAMES",
+        value_delimiter = ',',
+        help = "Signals to show, by path or name [default: the first top level scope]"
+    )]
+    pub signals: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "TIME",
+        help = "Start of the time window, in timestamps or with a unit (e.g. 20ns)"
+    )]
+    pub start: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TIME",
+        help = "End of the time window [default: the last timestamp]"
+    )]
+    pub end: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TIME",
+        help = "Time of the cursor, whose values are listed next to the names"
+    )]
+    pub cursor: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        value_name = "TIMES",
+        value_delimiter = ',',
+        help = "Times of markers, named A, B, ... in time order"
+    )]
+    pub markers: Vec<String>,
+
+    #[arg(short, long, default_value = "hex", help = "Radix of vector values")]
+    pub radix: Radix,
+
+    #[arg(long, default_value = "dark", help = "Color scheme")]
+    pub theme: Theme,
+}
+
+// ViewOptions resolved against a waveform
+#[derive(Clone, Debug, PartialEq)]
+pub struct View {
+    // Indexes into Waveform::signals
+    pub signals: Vec<usize>,
+    pub start: u64,
+    pub end: u64,
+    pub cursor: Option<u64>,
+    pub markers: Markers,
+    pub radix: Radix,
+    pub theme: Theme,
+}
+
+impl ViewOptions {
+    pub fn resolve(&self, waveform: &Waveform) -> Result<View, Box<dyn Error>> {
+        let signals = if self.signals.is_empty() {
+            match waveform.top_scopes.first() {
+                Some(scope) => waveform.scopes[*scope].signals.clone(),
+                None => waveform.top_signals.clone(),
+            }
+        } else {
+            self.signals
+                .iter()
+                .map(|name| {
+                    waveform
+                        .find_signal(name)
+                        .ok_or_else(|| format!("no signal named '{}'", name))
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        let time = |text: &str| parse_time(text, waveform.timescale);
+        let start = self.start.as_deref().map_or(Ok(0), time)?;
+        // Without an end, the window covers the whole dump, and still a time
+        // after the start when every change is at the same timestamp
+        let end = match self.end.as_deref() {
+            Some(end) => time(end)?,
+            None => waveform.last_timestamp().max(start + 1),
+        };
+        if start >= end {
+            return Err(format!(
+                "the time window starts at {} but ends at {}",
+                format_time(start, waveform.timescale),
+                format_time(end, waveform.timescale)
+            )
+            .into());
+        }
+
+        let cursor = self.cursor.as_deref().map(time).transpose()?;
+
+        let mut markers = Markers::default();
+        for text in self.markers.iter() {
+            markers
+                .add(time(text)?)
+                .ok_or("at most 26 markers can be placed")?;
+        }
+
+        Ok(View {
+            signals,
+            start,
+            end,
+            cursor,
+            markers,
+            radix: self.radix,
+            theme: self.theme,
+        })
+    }
+}
+
+// Header fields and size of a dump, one per line
+pub fn describe_waveform(waveform: &Waveform) -> String {
+    let mut lines = Vec::new();
+
+    if let Some(version) = &waveform.version {
+        lines.push(format!("version: {}", version.trim()));
+    }
+    if let Some(date) = &waveform.date {
+        lines.push(format!("date: {}", date.trim()));
+    }
+    if let Some((magnitude, unit)) = waveform.timescale {
+        lines.push(format!("timescale: {} {}", magnitude, unit));
+    }
+    lines.push(format!("scopes: {}", waveform.scopes.len()));
+    lines.push(format!("signals: {}", waveform.signals.len()));
+    lines.push(format!(
+        "changes: {}",
+        waveform.changes.values().map(Vec::len).sum::<usize>()
+    ));
+    lines.push(format!(
+        "end time: {}",
+        format_time(waveform.last_timestamp(), waveform.timescale)
+    ));
+
+    lines.join("\n")
+}
+
+// Path, width and type of every signal whose path matches `pattern`, one per line
+pub fn list_signals(waveform: &Waveform, pattern: Option<&str>) -> String {
+    waveform
+        .signals
+        .iter()
+        .map(|signal| (waveform.signal_path(signal), signal))
+        .filter(|(path, _)| pattern.is_none_or(|pattern| glob_match(pattern, path)))
+        .map(|(path, signal)| format!("{}\t{}\t{}", path, signal.size, signal.var_type))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    use crate::utils::waveform::tests::load_fixture;
+
+    #[test]
+    fn command_line_is_well_formed() {
+        Cli::command().debug_assert();
+
+        assert!(Cli::try_parse_from(["waveplot"]).is_err());
+        assert!(Cli::try_parse_from(["waveplot", "info", "dump.vcd"]).is_ok());
+        assert!(Cli::try_parse_from(["waveplot", "dump.vcd", "--radix", "octal"]).is_ok());
+        assert!(Cli::try_parse_from(["waveplot", "dump.vcd", "--radix", "roman"]).is_err());
+    }
+
+    #[test]
+    fn options_are_resolved_against_the_waveform() {
+        let waveform = load_fixture("test6.vcd");
+        let options = |args: &[&str]| {
+            Cli::try_parse_from([&["waveplot", "test6.vcd"], args].concat())
+                .unwrap()
+                .view
+        };
+
+        let view = options(&["-s", "dut.pc,alu.y", "--start", "10ns", "-r", "unsigned"])
+            .resolve(&waveform)
+            .unwrap();
+        assert_eq!(view.signals, vec![4, 7]);
+        assert_eq!((view.start, view.end), (10, waveform.last_timestamp()));
+        assert_eq!(view.radix, Radix::Unsigned);
+
+        assert!(options(&["-s", "missing"]).resolve(&waveform).is_err());
+        assert!(options(&["--start", "20", "--end", "10"])
+            .resolve(&waveform)
+            .is_err());
+    }
+
+    #[test]
+    fn dumps_with_a_single_timestamp_have_a_window() {
+        let waveform = load_fixture("test7.vcd");
+        let options = |args: &[&str]| {
+            Cli::try_parse_from([&["waveplot", "test7.vcd"], args].concat())
+                .unwrap()
+                .view
+        };
+
+        assert_eq!(waveform.last_timestamp(), 0);
+        let view = options(&[]).resolve(&waveform).unwrap();
+        assert_eq!((view.start, view.end), (0, 1));
+        let view = options(&["--start", "5"]).resolve(&waveform).unwrap();
+        assert_eq!((view.start, view.end), (5, 6));
+        assert!(options(&["--end", "0"]).resolve(&waveform).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use clap::ValueEnum;
+
+use std::collections::BTreeSet;
+
+use super::{radix::Radix, trace::format_value, waveform::Waveform};
+
+// Layout of the rows of a CSV export
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum CsvMode {
+    // One row per timestamp with the value every signal holds then, one column per signal
+    Samples,
+    // One row per value change with its time, signal and new value
+    Changes,
+}
+
+// Values of signals over a time window as comma separated values, with a
+// header row naming the columns
+pub struct CsvExport<'a> {
+    waveform: &'a Waveform,
+    // Indexes into Waveform::signals
+    signals: &'a [usize],
+    start: u64,
+    end: u64,
+    // Per signal settings indexed like Waveform::signals, `radix` for the missing ones
+    radix: Radix,
+    radixes: &'a [Radix],
+    mode: CsvMode,
+    // Timestamps between samples, None for a sample at every change
+    period: Option<u64>,
+}
+
+impl<'a> CsvExport<'a> {
+    pub fn new(
+        waveform: &'a Waveform,
+        signals: &'a [usize],
+        start: u64,
+        end: u64,
+    ) -> CsvExport<'a> {
+        CsvExport {
+            waveform,
+            signals,
+            start,
+            end,
+            radix: Radix::Hexadecimal,
+            radixes: &[],
+            mode: CsvMode::Samples,
+            period: None,
+        }
+    }
+
+    pub fn radix(mut self, radix: Radix) -> CsvExport<'a> {
+        self.radix = radix;
+        self
+    }
+
+    pub fn radixes(mut self, radixes: &'a [Radix]) -> CsvExport<'a> {
+        self.radixes = radixes;
+        self
+    }
+
+    pub fn mode(mut self, mode: CsvMode) -> CsvExport<'a> {
+        self.mode = mode;
+        self
+    }
+
+    pub fn period(mut self, period: Option<u64>) -> CsvExport<'a> {
+        self.period = period.filter(|period| *period > 0);
+        self
+    }
+
+    fn signal_radix(&self, signal: usize) -> Radix {
+        self.radixes.get(signal).copied().unwrap_or(self.radix)
+    }
+
+    // Timestamps are written in the unit of the timescale, so that a column can be
+    // read as numbers without knowing the magnitude
+    fn time_header(&self) -> String {
+        match self.waveform.timescale {
+            Some((_, unit)) => format!("time_{}", unit),
+            None => String::from("time"),
+        }
+    }
+
+    fn time(&self, time: u64) -> String {
+        let magnitude = self
+            .waveform
+            .timescale
+            .map_or(1, |(magnitude, _)| magnitude);
+        (time * magnitude as u64).to_string()
+    }
+
+    // Start of the window and every change within it, or every period from the start
+    fn sample_times(&self) -> Vec<u64> {
+        if let Some(period) = self.period {
+            return (self.start..=self.end).step_by(period as usize).collect();
+        }
+
+        let mut times = BTreeSet::from([self.start]);
+        for index in self.signals.iter() {
+            let signal = &self.waveform.signals[*index];
+            times.extend(
+                self.waveform
+                    .signal_changes(signal)
+                    .iter()
+                    .map(|(time, _)| *time)
+                    .filter(|time| *time > self.start && *time <= self.end),
+            );
+        }
+        times.into_iter().collect()
+    }
+
+    pub fn render(&self) -> String {
+        let waveform = self.waveform;
+        let mut rows = Vec::new();
+
+        match self.mode {
+            CsvMode::Samples => {
+                let mut header = vec![self.time_header()];
+                header.extend(
+                    self.signals
+                        .iter()
+                        .map(|index| escape(&waveform.signal_path(&waveform.signals[*index]))),
+                );
+                rows.push(header.join(","));
+
+                // Signals without a value yet are left empty
+                for time in self.sample_times() {
+                    let mut row = vec![self.time(time)];
+                    row.extend(self.signals.iter().map(|index| {
+                        let signal = &waveform.signals[*index];
+                        waveform
+                            .value_at(signal, time)
+                            .map(|value| escape(&format_value(value, self.signal_radix(*index))))
+                            .unwrap_or_default()
+                    }));
+                    rows.push(row.join(","));
+                }
+            }
+            CsvMode::Changes => {
+                rows.push(format!("{},signal,value", self.time_header()));
+
+                let mut changes = Vec::new();
+                for (order, index) in self.signals.iter().enumerate() {
+                    let signal = &waveform.signals[*index];
+                    let name = escape(&waveform.signal_path(signal));
+                    for (time, value) in waveform.signal_changes(signal) {
+                        if *time >= self.start && *time <= self.end {
+                            let value = escape(&format_value(value, self.signal_radix(*index)));
+                            changes.push((*time, order, format!("{},{}", name, value)));
+                        }
+                    }
+                }
+
+                // Changes at the same time keep the order of the signals
+                changes.sort_by_key(|(time, order, _)| (*time, *order));
+                rows.extend(
+                    changes
+                        .into_iter()
+                        .map(|(time, _, change)| format!("{},{}", self.time(time), change)),
+                );
+            }
+        }
+
+        rows.push(String::new());
+        rows.join("\n")
+    }
+}
+
+// Quote a field holding a separator, a quote or a line break, doubling its quotes
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::waveform::tests::load_fixture;
+
+    #[test]
+    fn signals_are_sampled_at_every_change() {
+        let waveform = load_fixture("test6.vcd");
+        let signals = [
+            waveform.find_signal("clk").unwrap(),
+            waveform.find_signal("dut.pc").unwrap(),
+        ];
+
+        let csv = CsvExport::new(&waveform, &signals, 0, 20).render();
+        assert_eq!(
+            csv,
+            "time_ns,cpu_tb.clk,cpu_tb.dut.pc\n0,0,00\n5,1,00\n10,0,00\n15,1,01\n20,0,01\n"
+        );
+
+        let csv = CsvExport::new(&waveform, &signals, 10, 20)
+            .mode(CsvMode::Changes)
+            .render();
+        assert_eq!(
+            csv,
+            "time_ns,signal,value\n10,cpu_tb.clk,0\n15,cpu_tb.clk,1\n15,cpu_tb.dut.pc,01\n20,cpu_tb.clk,0\n"
+        );
+    }
+
+    #[test]
+    fn fields_are_quoted_when_needed() {
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("plain"), "plain");
+    }
+}
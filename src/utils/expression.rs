@@ -0,0 +1,455 @@
+use vcd::{ReferenceIndex, Value};
+
+use super::{
+    search::{parse_literal, resize, select_bits, to_integer, value_bits},
+    waveform::{SignalValue, Waveform},
+};
+
+// Operators of derived signal expressions, from the loosest to the tightest binding
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    LogicalOr,
+    LogicalAnd,
+    Or,
+    Xor,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Add,
+    Subtract,
+    Multiply,
+    // Unary operators
+    Not,
+    LogicalNot,
+    Negate,
+}
+
+// A derived signal such as `en & ~underrun` or `a + b`, over the signals of a waveform
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Signal(usize, Option<ReferenceIndex>),
+    Literal(Vec<Value>),
+    Unary(Operator, Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+}
+
+// Binary operators of every precedence level, loosest first
+const LEVELS: [&[(&str, Operator)]; 9] = [
+    &[("||", Operator::LogicalOr)],
+    &[("&&", Operator::LogicalAnd)],
+    &[("|", Operator::Or)],
+    &[("^", Operator::Xor)],
+    &[("&", Operator::And)],
+    &[("==", Operator::Equal), ("!=", Operator::NotEqual)],
+    &[
+        ("<=", Operator::LessEqual),
+        (">=", Operator::GreaterEqual),
+        ("<", Operator::Less),
+        (">", Operator::Greater),
+    ],
+    &[("+", Operator::Add), ("-", Operator::Subtract)],
+    &[("*", Operator::Multiply)],
+];
+
+// Recursive descent parser over the characters of an expression
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+    waveform: &'a Waveform,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_spaces(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    // Consume `token` if the expression continues with it, without taking the
+    // first character of a longer operator such as && for &
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_spaces();
+        let rest = self.rest();
+        if !rest.starts_with(token) {
+            return false;
+        }
+        let longer = ["&&", "||", "==", "!=", "<=", ">="]
+            .iter()
+            .any(|operator| operator.len() > token.len() && rest.starts_with(operator));
+        if longer {
+            return false;
+        }
+        self.position += token.len();
+        true
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expression, String> {
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        'operators: loop {
+            for (token, operator) in LEVELS[level] {
+                if self.eat(token) {
+                    let right = self.binary(level + 1)?;
+                    left = Expression::Binary(*operator, Box::new(left), Box::new(right));
+                    continue 'operators;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        for (token, operator) in [
+            ("~", Operator::Not),
+            ("!", Operator::LogicalNot),
+            ("-", Operator::Negate),
+        ] {
+            if self.eat(token) {
+                return Ok(Expression::Unary(operator, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, String> {
+        if self.eat("(") {
+            let expression = self.binary(0)?;
+            if !self.eat(")") {
+                return Err(String::from("expected ')'"));
+            }
+            return Ok(expression);
+        }
+
+        self.skip_spaces();
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || "_.$'".contains(c)))
+            .unwrap_or(rest.len());
+        let word = &rest[..length];
+        if word.is_empty() {
+            return Err(match rest.chars().next() {
+                Some(c) => format!("unexpected '{}'", c),
+                None => String::from("unexpected end of expression"),
+            });
+        }
+        self.position += length;
+
+        if word.starts_with(|c: char| c.is_ascii_digit() || c == '\'') {
+            return parse_literal(word).map(Expression::Literal);
+        }
+
+        let signal = self
+            .waveform
+            .find_signal(word)
+            .ok_or_else(|| format!("no signal named '{}'", word))?;
+
+        // Optional bit select, e.g. addr[7:4]
+        self.skip_spaces();
+        let slice = match self.rest().find(']') {
+            Some(end) if self.rest().starts_with('[') => {
+                let text = &self.rest()[..=end];
+                let slice = text
+                    .parse::<ReferenceIndex>()
+                    .map_err(|_| format!("invalid bit select '{}'", text))?;
+                self.position += end + 1;
+                Some(slice)
+            }
+            _ => None,
+        };
+
+        Ok(Expression::Signal(signal, slice))
+    }
+}
+
+impl Expression {
+    pub fn parse(text: &str, waveform: &Waveform) -> Result<Expression, String> {
+        let mut parser = Parser {
+            text,
+            position: 0,
+            waveform,
+        };
+        let expression = parser.binary(0)?;
+
+        parser.skip_spaces();
+        match parser.rest().chars().next() {
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Ok(expression),
+        }
+    }
+
+    // Signals read by the expression
+    fn signals(&self, signals: &mut Vec<usize>) {
+        match self {
+            Expression::Signal(signal, _) => signals.push(*signal),
+            Expression::Literal(_) => {}
+            Expression::Unary(_, operand) => operand.signals(signals),
+            Expression::Binary(_, left, right) => {
+                left.signals(signals);
+                right.signals(signals);
+            }
+        }
+    }
+
+    // Bits of the expression at `time`, most significant first
+    pub fn evaluate(&self, waveform: &Waveform, time: u64) -> Vec<Value> {
+        match self {
+            Expression::Signal(index, slice) => {
+                let signal = &waveform.signals[*index];
+                let unknown = vec![Value::X; signal.size as usize];
+                match (waveform.value_at(signal, time), slice) {
+                    (Some(value), Some(slice)) => {
+                        select_bits(signal, value, *slice).unwrap_or(unknown)
+                    }
+                    (Some(value), None) => {
+                        let bits = value_bits(value);
+                        if bits.is_empty() {
+                            unknown
+                        } else {
+                            bits
+                        }
+                    }
+                    (None, _) => unknown,
+                }
+            }
+            Expression::Literal(bits) => bits.clone(),
+            Expression::Unary(operator, operand) => {
+                unary(*operator, &operand.evaluate(waveform, time))
+            }
+            Expression::Binary(operator, left, right) => binary(
+                *operator,
+                &left.evaluate(waveform, time),
+                &right.evaluate(waveform, time),
+            ),
+        }
+    }
+
+    // Value changes of the expression, evaluated at every change of the signals it reads
+    pub fn changes(&self, waveform: &Waveform) -> Vec<(u64, SignalValue)> {
+        self.changes_since(waveform, 0)
+    }
+
+    // Value changes of the expression at the changes of the signals it reads from
+    // `from` on, to extend those already worked out before it
+    pub fn changes_since(&self, waveform: &Waveform, from: u64) -> Vec<(u64, SignalValue)> {
+        let mut signals = Vec::new();
+        self.signals(&mut signals);
+
+        let mut times: Vec<u64> = signals
+            .iter()
+            .flat_map(|signal| {
+                let changes = waveform.signal_changes(&waveform.signals[*signal]);
+                let first = changes.partition_point(|(time, _)| *time < from);
+                changes[first..].iter().map(|(time, _)| *time)
+            })
+            .collect();
+        if times.is_empty() && from == 0 {
+            times.push(0);
+        }
+        times.sort_unstable();
+        times.dedup();
+
+        let mut changes: Vec<(u64, SignalValue)> = Vec::new();
+        for time in times {
+            let bits = self.evaluate(waveform, time);
+            let value = match bits.as_slice() {
+                [bit] => SignalValue::Scalar(*bit),
+                bits => SignalValue::Vector(bits.iter().copied().collect()),
+            };
+            if changes.last().map(|(_, last)| last) != Some(&value) {
+                changes.push((time, value));
+            }
+        }
+
+        changes
+    }
+}
+
+// Four state bitwise logic, z read as x
+fn bitwise(operator: Operator, left: Value, right: Value) -> Value {
+    use Value::{V0, V1, X};
+
+    match (operator, left, right) {
+        (Operator::And, V0, _) | (Operator::And, _, V0) => V0,
+        (Operator::And, V1, V1) => V1,
+        (Operator::Or, V1, _) | (Operator::Or, _, V1) => V1,
+        (Operator::Or, V0, V0) => V0,
+        (Operator::Xor, V0 | V1, V0 | V1) => {
+            if left == right {
+                V0
+            } else {
+                V1
+            }
+        }
+        _ => X,
+    }
+}
+
+// 1 when any bit is 1, 0 when every bit is 0, x otherwise
+fn truth(bits: &[Value]) -> Value {
+    if bits.contains(&Value::V1) {
+        Value::V1
+    } else if bits.iter().all(|bit| *bit == Value::V0) {
+        Value::V0
+    } else {
+        Value::X
+    }
+}
+
+// The `width` least significant bits of a number
+fn from_integer(value: u128, width: usize) -> Vec<Value> {
+    (0..width)
+        .rev()
+        .map(|bit| {
+            if bit < 128 && value >> bit & 1 == 1 {
+                Value::V1
+            } else {
+                Value::V0
+            }
+        })
+        .collect()
+}
+
+fn unary(operator: Operator, operand: &[Value]) -> Vec<Value> {
+    match operator {
+        Operator::Not => operand
+            .iter()
+            .map(|bit| match bit {
+                Value::V0 => Value::V1,
+                Value::V1 => Value::V0,
+                _ => Value::X,
+            })
+            .collect(),
+        Operator::LogicalNot => vec![match truth(operand) {
+            Value::V0 => Value::V1,
+            Value::V1 => Value::V0,
+            _ => Value::X,
+        }],
+        _ => match to_integer(operand) {
+            Some(value) => from_integer(value.wrapping_neg(), operand.len()),
+            None => vec![Value::X; operand.len()],
+        },
+    }
+}
+
+fn binary(operator: Operator, left: &[Value], right: &[Value]) -> Vec<Value> {
+    let width = left.len().max(right.len());
+
+    match operator {
+        Operator::And | Operator::Or | Operator::Xor => {
+            let (left, right) = (resize(left, width), resize(right, width));
+            left.iter()
+                .zip(right.iter())
+                .map(|(left, right)| bitwise(operator, *left, *right))
+                .collect()
+        }
+        Operator::LogicalAnd | Operator::LogicalOr => {
+            let operator = match operator {
+                Operator::LogicalAnd => Operator::And,
+                _ => Operator::Or,
+            };
+            vec![bitwise(operator, truth(left), truth(right))]
+        }
+        _ => {
+            // Arithmetic and comparisons are unknown as soon as one bit is
+            let width = match operator {
+                Operator::Add => width + 1,
+                Operator::Multiply => left.len() + right.len(),
+                _ => width,
+            }
+            .min(128);
+            let (Some(left), Some(right)) = (to_integer(left), to_integer(right)) else {
+                return match operator {
+                    Operator::Add | Operator::Subtract | Operator::Multiply => {
+                        vec![Value::X; width]
+                    }
+                    _ => vec![Value::X],
+                };
+            };
+
+            let compared = |result: bool| vec![if result { Value::V1 } else { Value::V0 }];
+            match operator {
+                Operator::Add => from_integer(left.wrapping_add(right), width),
+                Operator::Subtract => from_integer(left.wrapping_sub(right), width),
+                Operator::Multiply => from_integer(left.wrapping_mul(right), width),
+                Operator::Equal => compared(left == right),
+                Operator::NotEqual => compared(left != right),
+                Operator::Less => compared(left < right),
+                Operator::LessEqual => compared(left <= right),
+                Operator::Greater => compared(left > right),
+                _ => compared(left >= right),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::waveform::tests::load_fixture;
+
+    fn changes(text: &str) -> Vec<(u64, String)> {
+        let waveform = load_fixture("test6.vcd");
+
+        Expression::parse(text, &waveform)
+            .unwrap()
+            .changes(&waveform)
+            .iter()
+            .map(|(time, value)| {
+                let text = match value {
+                    SignalValue::Scalar(value) => value.to_string(),
+                    SignalValue::Vector(vector) => vector.to_string(),
+                    _ => String::new(),
+                };
+                (*time, text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bitwise_expressions_follow_every_operand() {
+        assert_eq!(
+            changes("clk & ~carry"),
+            vec![
+                (0, String::from("0")),
+                (5, String::from("x")),
+                (10, String::from("0")),
+                (15, String::from("1")),
+                (20, String::from("0")),
+            ]
+        );
+        assert_eq!(changes("!rst_n || clk")[0], (0, String::from("1")));
+    }
+
+    #[test]
+    fn arithmetic_widens_and_propagates_unknowns() {
+        assert_eq!(
+            changes("a + b"),
+            vec![
+                (0, String::from("xxxxxxxxx")),
+                (15, String::from("000001000")),
+                (25, String::from("100000000")),
+            ]
+        );
+        assert_eq!(changes("pc[1:0] == 2'b11")[1], (35, String::from("1")));
+    }
+
+    #[test]
+    fn invalid_expressions_are_reported() {
+        let waveform = load_fixture("test6.vcd");
+
+        assert!(Expression::parse("a +", &waveform).is_err());
+        assert!(Expression::parse("(a", &waveform).is_err());
+        assert!(Expression::parse("missing & a", &waveform).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use fst_reader::{
+    FstFilter, FstHierarchyEntry, FstReader, FstScopeType, FstSignalValue, FstVarType,
+};
+use vcd::{Header, IdCode, Scope, ScopeItem, ScopeType, TimescaleUnit, Value, Var, VarType};
+
+use std::{collections::HashMap, error::Error, fs::File, io::BufReader, path::Path};
+
+use super::{
+    loader::{Batch, MAX_LOG_LINES},
+    source::Source,
+    waveform::{SignalValue, Waveform},
+};
+
+// Read the FST file at `path` into the waveform a VCD file with the same
+// hierarchy and changes would give
+pub fn load(path: &Path) -> Result<Waveform, Box<dyn Error>> {
+    let in_path = |err: &dyn Error| format!("{}: {}", path.display(), err);
+    let file = File::open(path).map_err(|err| in_path(&err))?;
+    let mut reader =
+        FstReader::open_and_read_time_table(BufReader::new(file)).map_err(|err| in_path(&err))?;
+
+    let mut entries = Vec::new();
+    reader
+        .read_hierarchy(|entry| entries.push(entry))
+        .map_err(|err| in_path(&err))?;
+    let (header, types) = to_header(&reader.get_header(), entries).map_err(|err| in_path(&*err))?;
+    let mut waveform = Waveform::from_header(header);
+    waveform.source = Source::Text(String::from(
+        "(FST files are binary, their changes are listed on the Parser tab)",
+    ));
+
+    let mut batch = Batch {
+        timestamps: reader.get_time_table().unwrap_or_default().to_vec(),
+        ..Batch::default()
+    };
+    let mut last_time = None;
+    reader
+        .read_signals(&FstFilter::all(), |time, handle, value| {
+            let code = IdCode::from(handle.get_index() as u64);
+            let value = signal_value(value, types.get(&code).copied());
+
+            if batch.log.len() < MAX_LOG_LINES {
+                if last_time != Some(time) {
+                    batch.log.push(format!("Timestamp: {:?}", time));
+                    last_time = Some(time);
+                }
+                batch.log.push(format!(
+                    "{:?} changed to {}",
+                    code.to_string(),
+                    describe(&value)
+                ));
+            } else if batch.log.len() == MAX_LOG_LINES {
+                batch.log.push(format!(
+                    "Only the first {} changes are listed",
+                    MAX_LOG_LINES
+                ));
+            }
+            batch.changes.push((code, time, value));
+        })
+        .map_err(|err| in_path(&err))?;
+
+    // The time table starts at 0 even without initial values, which a VCD
+    // file would not list
+    if batch.timestamps.first() == Some(&0) && !batch.changes.iter().any(|(_, time, _)| *time == 0)
+    {
+        batch.timestamps.remove(0);
+    }
+
+    waveform.add_batch(batch);
+    Ok(waveform)
+}
+
+// Header of a VCD file declaring the scopes and variables of an FST hierarchy,
+// with the type of the variable behind every code
+fn to_header(
+    fst: &fst_reader::FstHeader,
+    entries: Vec<FstHierarchyEntry>,
+) -> Result<(Header, HashMap<IdCode, VarType>), Box<dyn Error>> {
+    let mut header = Header::default();
+    header.version = Some(fst.version.clone()).filter(|version| !version.is_empty());
+    header.date = Some(fst.date.clone()).filter(|date| !date.is_empty());
+    header.timescale = Some(timescale(fst.timescale_exponent)?);
+
+    // Scopes opened but not closed yet, innermost last
+    let mut open: Vec<Scope> = Vec::new();
+    let mut types = HashMap::new();
+    for entry in entries {
+        let item = match entry {
+            FstHierarchyEntry::Scope { tpe, name, .. } => {
+                open.push(Scope::new(scope_type(tpe), name));
+                continue;
+            }
+            FstHierarchyEntry::UpScope => match open.pop() {
+                Some(scope) => ScopeItem::Scope(scope),
+                None => continue,
+            },
+            FstHierarchyEntry::Var {
+                tpe,
+                name,
+                length,
+                handle,
+                ..
+            } => {
+                let code = IdCode::from(handle.get_index() as u64);
+                let var_type = var_type(tpe);
+                types.insert(code, var_type);
+
+                // Vectors are named with their range, as in "pc [7:0]"
+                let (reference, index) = match name.split_once(' ') {
+                    Some((reference, index)) => match index.parse() {
+                        Ok(index) => (reference.to_string(), Some(index)),
+                        Err(_) => (name.clone(), None),
+                    },
+                    None => (name, None),
+                };
+                ScopeItem::Var(Var::new(var_type, length, code, reference, index))
+            }
+            _ => continue,
+        };
+
+        match open.last_mut() {
+            Some(scope) => scope.items.push(item),
+            None => header.items.push(item),
+        }
+    }
+    // A hierarchy cut short still declares the scopes it opened
+    while let Some(scope) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => parent.items.push(ScopeItem::Scope(scope)),
+            None => header.items.push(ScopeItem::Scope(scope)),
+        }
+    }
+
+    Ok((header, types))
+}
+
+// Timescale of 10^exponent seconds, e.g. (100, ps) for -10. VCD timescales go
+// from 1 fs to 100 s
+fn timescale(exponent: i8) -> Result<(u32, TimescaleUnit), String> {
+    if !(-15..=2).contains(&exponent) {
+        return Err(format!(
+            "a timescale of 1e{} s is outside of the 1 fs to 100 s of VCD files",
+            exponent
+        ));
+    }
+
+    let units = [
+        (0, TimescaleUnit::S),
+        (-3, TimescaleUnit::MS),
+        (-6, TimescaleUnit::US),
+        (-9, TimescaleUnit::NS),
+        (-12, TimescaleUnit::PS),
+        (-15, TimescaleUnit::FS),
+    ];
+    let (unit_exponent, unit) = units
+        .into_iter()
+        .find(|(unit_exponent, _)| exponent >= *unit_exponent)
+        .unwrap_or((-15, TimescaleUnit::FS));
+    Ok((10u32.pow((exponent - unit_exponent) as u32), unit))
+}
+
+// VCD scope and variable types for those of FST, which also has VHDL and
+// SystemVerilog ones
+fn scope_type(fst: FstScopeType) -> ScopeType {
+    match fst {
+        FstScopeType::Task => ScopeType::Task,
+        FstScopeType::Function => ScopeType::Function,
+        FstScopeType::Begin => ScopeType::Begin,
+        FstScopeType::Fork => ScopeType::Fork,
+        _ => ScopeType::Module,
+    }
+}
+
+fn var_type(fst: FstVarType) -> VarType {
+    match fst {
+        fst if fst.is_real() => VarType::Real,
+        FstVarType::Event => VarType::Event,
+        FstVarType::Integer
+        | FstVarType::Int
+        | FstVarType::ShortInt
+        | FstVarType::LongInt
+        | FstVarType::Byte => VarType::Integer,
+        FstVarType::Parameter => VarType::Parameter,
+        FstVarType::Supply0 => VarType::Supply0,
+        FstVarType::Supply1 => VarType::Supply1,
+        FstVarType::Time => VarType::Time,
+        FstVarType::Tri => VarType::Tri,
+        FstVarType::TriAnd => VarType::TriAnd,
+        FstVarType::TriOr => VarType::TriOr,
+        FstVarType::TriReg => VarType::TriReg,
+        FstVarType::Tri0 => VarType::Tri0,
+        FstVarType::Tri1 => VarType::Tri1,
+        FstVarType::Wand => VarType::WAnd,
+        FstVarType::Wire | FstVarType::Port => VarType::Wire,
+        FstVarType::Wor => VarType::WOr,
+        FstVarType::GenericString => VarType::String,
+        _ => VarType::Reg,
+    }
+}
+
+// Value of a change of a variable of type `var_type`. FST has nine logic values,
+// the weak ones are read as their strong counterparts and the others as X
+fn signal_value(value: FstSignalValue, var_type: Option<VarType>) -> SignalValue {
+    let bytes = match value {
+        FstSignalValue::Real(value) => return SignalValue::Real(value),
+        FstSignalValue::String(bytes) => bytes,
+    };
+    if var_type == Some(VarType::String) {
+        return SignalValue::String(String::from_utf8_lossy(bytes).into_owned());
+    }
+
+    let bits: Vec<Value> = bytes
+        .iter()
+        .map(|byte| match byte {
+            b'0' | b'l' | b'L' => Value::V0,
+            b'1' | b'h' | b'H' => Value::V1,
+            b'z' | b'Z' => Value::Z,
+            _ => Value::X,
+        })
+        .collect();
+    match bits.as_slice() {
+        [bit] => SignalValue::Scalar(*bit),
+        _ => SignalValue::Vector(bits.into()),
+    }
+}
+
+// Value as the Parser tab lists those of VCD files
+fn describe(value: &SignalValue) -> String {
+    match value {
+        SignalValue::Scalar(value) => format!("{:?}", value),
+        SignalValue::Vector(value) => format!("{:?}", value.to_string()),
+        SignalValue::Real(value) => format!("{:?}", value),
+        SignalValue::String(value) => format!("{:?}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::waveform::tests::load_fixture;
+
+    #[test]
+    fn fst_files_read_like_the_same_vcd() {
+        let fst = load_fixture("test6.fst");
+        let vcd = load_fixture("test6.vcd");
+
+        assert_eq!(fst.version, vcd.version);
+        assert_eq!(fst.timescale, vcd.timescale);
+        assert_eq!(fst.timestamps, vcd.timestamps);
+        assert_eq!(fst.signals.len(), vcd.signals.len());
+        for (a, b) in fst.signals.iter().zip(&vcd.signals) {
+            assert_eq!(fst.signal_path(a), vcd.signal_path(b));
+            assert_eq!((a.size, a.index), (b.size, b.index));
+            assert_eq!(fst.signal_changes(a), vcd.signal_changes(b));
+        }
+    }
+
+    #[test]
+    fn timescales_are_read_from_exponents() {
+        assert_eq!(timescale(-9), Ok((1, TimescaleUnit::NS)));
+        assert_eq!(timescale(-10), Ok((100, TimescaleUnit::PS)));
+        assert_eq!(timescale(-5), Ok((10, TimescaleUnit::US)));
+        assert_eq!(timescale(0), Ok((1, TimescaleUnit::S)));
+        assert_eq!(timescale(-15), Ok((1, TimescaleUnit::FS)));
+        assert_eq!(timescale(2), Ok((100, TimescaleUnit::S)));
+        assert!(timescale(-16).is_err());
+        assert!(timescale(3).is_err());
+    }
+}
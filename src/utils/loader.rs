@@ -0,0 +1,688 @@
+use flate2::bufread::MultiGzDecoder;
+use vcd::{Command, Header, IdCode, Parser, Value, Vector};
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    source::{Source, TrackedReader},
+    waveform::{SignalValue, Waveform},
+};
+
+// Commands read between two batches handed over to the waveform
+const BATCH_COMMANDS: usize = 1 << 16;
+
+// Batches read ahead of the waveform, bounding the memory the queue takes
+// when they are applied slower than they are read
+const QUEUE_BATCHES: usize = 4;
+
+// Commands listed on the Parser tab, the log of a large dump would outgrow its changes
+pub const MAX_LOG_LINES: usize = 100_000;
+
+// First bytes of gzip and zstd streams
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+// Header block of an FST file, its type and fixed length
+const FST_MAGIC: [u8; 9] = [0, 0, 0, 0, 0, 0, 0, 0x01, 0x49];
+
+// How often a file being followed is checked for new lines
+const FOLLOW_POLL: Duration = Duration::from_millis(200);
+
+// Bytes read at once from a file being followed
+const FOLLOW_CHUNK: usize = 1 << 16;
+
+// Changes read since the last batch, and how far into the file they go
+#[derive(Default)]
+pub struct Batch {
+    pub changes: Vec<(IdCode, u64, SignalValue)>,
+    pub timestamps: Vec<u64>,
+    pub log: Vec<String>,
+    pub bytes: u64,
+    pub lines: usize,
+    // Line index entries added since the last batch
+    pub line_starts: Vec<u64>,
+    // Text read since the last batch, when it can't be read again from a file
+    pub text: String,
+}
+
+enum Message {
+    Batch(Batch),
+    Failed(String),
+}
+
+// Reads the changes of a VCD file on a thread of its own, so that the waveform
+// can be browsed while a large file is still being read
+pub struct Loader {
+    receiver: Receiver<Message>,
+    // Tells the thread following a file to stop once the loader is dropped
+    stop: Arc<AtomicBool>,
+    // Size of the file and bytes read from it so far
+    pub total: u64,
+    pub read: u64,
+    pub done: bool,
+    pub error: Option<String>,
+    // Whether the file is still being written, and read as it grows
+    pub following: bool,
+}
+
+impl Loader {
+    // Read the header of the VCD file at `path` (stdin for "-"), returning the
+    // waveform it declares and a loader adding the changes that follow
+    pub fn open(path: &Path) -> Result<(Waveform, Loader), Box<dyn Error>> {
+        let in_path = |err: &dyn Error| format!("{}: {}", path.display(), err);
+        let input = Input::open(path).map_err(|err| in_path(&err))?;
+
+        let mut reader = TrackedReader::new(input.reader);
+        if !input.rereadable {
+            reader = reader.keep_text();
+        }
+        let mut parser = Parser::new(reader);
+        let header = parser.parse_header()?;
+        Ok(Loader::start(parser, header, path, input.size, false))
+    }
+
+    // Like open, for a VCD file a running simulation is still writing: waits for its
+    // header to be complete, then keeps adding the changes appended to it
+    pub fn follow(path: &Path) -> Result<(Waveform, Loader), Box<dyn Error>> {
+        let in_path = |err: &dyn Error| format!("{}: {}", path.display(), err);
+        loop {
+            let mut file = File::open(path).map_err(|err| in_path(&err))?;
+            let total = file.metadata().map_err(|err| in_path(&err))?.len();
+
+            // Compressed and FST files can't be read a line at a time as they grow
+            let mut magic = Vec::new();
+            (&file)
+                .take(FST_MAGIC.len() as u64)
+                .read_to_end(&mut magic)
+                .and_then(|_| file.rewind())
+                .map_err(|err| in_path(&err))?;
+            if [&GZIP_MAGIC[..], &ZSTD_MAGIC, &FST_MAGIC]
+                .iter()
+                .any(|binary| magic.starts_with(binary))
+            {
+                return Err(
+                    format!("{}: only plain VCD files can be followed", path.display()).into(),
+                );
+            }
+
+            let mut parser = Parser::new(TrackedReader::new(FollowReader::new(file)));
+            match parser.parse_header() {
+                Ok(header) => return Ok(Loader::start(parser, header, path, total, true)),
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    thread::sleep(FOLLOW_POLL)
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    // Read the changes after the header already parsed on a thread of their own
+    fn start<R: BufRead + Send + 'static>(
+        mut parser: Parser<TrackedReader<R>>,
+        header: Header,
+        path: &Path,
+        total: u64,
+        following: bool,
+    ) -> (Waveform, Loader) {
+        let mut waveform = Waveform::from_header(header);
+
+        waveform.source = match parser.reader().text.as_mut() {
+            Some(text) => Source::Text(String::from_utf8_lossy(&std::mem::take(text)).into_owned()),
+            None => Source::File {
+                path: PathBuf::from(path),
+                line_starts: Vec::new(),
+                lines: 0,
+            },
+        };
+
+        let widths = waveform
+            .signals
+            .iter()
+            .map(|signal| (signal.code, signal.size as usize))
+            .collect();
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_BATCHES);
+        let stop = Arc::new(AtomicBool::new(false));
+        let follow = following.then(|| Arc::clone(&stop));
+        thread::spawn(move || {
+            if let Err(err) = read_changes(parser, widths, &sender, follow) {
+                let _ = sender.send(Message::Failed(err));
+            }
+        });
+
+        let loader = Loader {
+            receiver,
+            stop,
+            total,
+            read: 0,
+            done: false,
+            error: None,
+            following,
+        };
+        (waveform, loader)
+    }
+
+    // Add the batches read so far to `waveform`, returning whether there were any
+    pub fn poll(&mut self, waveform: &mut Waveform) -> bool {
+        let mut changed = false;
+        while !self.done {
+            match self.receiver.try_recv() {
+                Ok(message) => {
+                    self.receive(message, waveform);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.done = true,
+            }
+        }
+        changed
+    }
+
+    // Add batches to `waveform` until the file is read or `timeout` has passed
+    pub fn wait(&mut self, waveform: &mut Waveform, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        while !self.done {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(timeout) {
+                Ok(message) => self.receive(message, waveform),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => self.done = true,
+            }
+        }
+    }
+
+    // Add every remaining batch to `waveform`, failing if the file is invalid
+    pub fn finish(&mut self, waveform: &mut Waveform) -> Result<(), Box<dyn Error>> {
+        while let Ok(message) = self.receiver.recv() {
+            self.receive(message, waveform);
+        }
+        self.done = true;
+        match self.error.take() {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    // Share of the file read, from 0 to 1
+    pub fn progress(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.read as f64 / self.total as f64).min(1.0)
+        }
+    }
+
+    fn receive(&mut self, message: Message, waveform: &mut Waveform) {
+        match message {
+            Message::Batch(batch) => {
+                self.read = batch.bytes;
+                // A file being followed outgrows the size it had when opened
+                self.total = self.total.max(self.read);
+                waveform.add_batch(batch);
+            }
+            Message::Failed(err) => {
+                self.error = Some(err);
+                self.done = true;
+            }
+        }
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Reader of a file still being written, handing out whole lines only so that the
+// parser reaches its end between two commands rather than within one being written
+struct FollowReader {
+    file: File,
+    buffer: Vec<u8>,
+    // Start of the bytes not read yet, and end of the last whole line after them
+    position: usize,
+    lines_end: usize,
+    // Bytes read from the file
+    offset: u64,
+}
+
+impl FollowReader {
+    fn new(file: File) -> FollowReader {
+        FollowReader {
+            file,
+            buffer: Vec::new(),
+            position: 0,
+            lines_end: 0,
+            offset: 0,
+        }
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl BufRead for FollowReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position == self.lines_end {
+            self.buffer.drain(..self.position);
+            self.position = 0;
+
+            let kept = self.buffer.len();
+            self.buffer.resize(kept + FOLLOW_CHUNK, 0);
+            let read = self.file.read(&mut self.buffer[kept..])?;
+            self.buffer.truncate(kept + read);
+            // A simulation started again writes the file from the start
+            if read == 0 && self.file.metadata()?.len() < self.offset {
+                return Err(io::Error::other("the file was truncated"));
+            }
+            self.offset += read as u64;
+            self.lines_end = self
+                .buffer
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .map_or(0, |last| last + 1);
+        }
+        Ok(&self.buffer[self.position..self.lines_end])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount;
+    }
+}
+
+// Read the commands following the header, sending the changes in batches. Stops
+// quietly when nobody is left to receive them. With `follow`, the end of the file
+// is read again as it grows, until the flag in `follow` is set
+fn read_changes<R: BufRead>(
+    mut parser: Parser<TrackedReader<R>>,
+    widths: HashMap<IdCode, usize>,
+    sender: &SyncSender<Message>,
+    follow: Option<Arc<AtomicBool>>,
+) -> Result<(), String> {
+    let mut indexed = 0;
+    let mut sent = 0;
+    let mut batch = Batch::default();
+    let mut commands: usize = 0;
+    let mut logged = 0;
+    let mut log = |batch: &mut Batch, line: String| {
+        if logged < MAX_LOG_LINES {
+            batch.log.push(line);
+        } else if logged == MAX_LOG_LINES {
+            batch.log.push(format!(
+                "Only the first {} commands are listed",
+                MAX_LOG_LINES
+            ));
+        }
+        logged += 1;
+    };
+
+    // Time of the last timestamp, changes before the first one (usually
+    // $dumpvars) happen at time 0 and add it to the timestamps like an FST
+    // time table would
+    let mut current_time = None;
+
+    loop {
+        let Some(command) = parser.next() else {
+            match &follow {
+                Some(stop) if !stop.load(Ordering::Relaxed) => {
+                    if parser.reader().bytes > sent {
+                        let batch = tracked(&mut parser, &mut indexed, std::mem::take(&mut batch));
+                        sent = batch.bytes;
+                        if sender.send(Message::Batch(batch)).is_err() {
+                            return Ok(());
+                        }
+                    }
+                    thread::sleep(FOLLOW_POLL);
+                    continue;
+                }
+                _ => break,
+            }
+        };
+
+        let command = command.map_err(|err| err.to_string())?;
+        let change = matches!(
+            command,
+            Command::ChangeScalar(..)
+                | Command::ChangeVector(..)
+                | Command::ChangeReal(..)
+                | Command::ChangeString(..)
+        );
+        if change && current_time.is_none() {
+            batch.timestamps.push(0);
+            current_time = Some(0);
+        }
+        let now = current_time.unwrap_or(0);
+
+        match command {
+            Command::Begin(id) => {
+                log(&mut batch, format!("Begin: {:?}", id));
+            }
+            Command::End(id) => {
+                log(&mut batch, format!("End: {:?}", id));
+            }
+            Command::Comment(comment) => {
+                log(&mut batch, format!("Comment: {:?}", comment));
+            }
+            Command::Date(date) => {
+                log(&mut batch, format!("Date: {:?}", date));
+            }
+            Command::Version(version) => {
+                log(&mut batch, format!("Version: {:?}", version));
+            }
+            Command::Timescale(time, unit) => {
+                log(&mut batch, format!("Timescale: {:?} {:?}", time, unit));
+            }
+            Command::ScopeDef(scope_type, name) => {
+                log(
+                    &mut batch,
+                    format!("Scope Def: {:?} {:?}", scope_type, name),
+                );
+            }
+            Command::Upscope => {
+                log(&mut batch, "Upscope".to_string());
+            }
+            Command::Enddefinitions => {
+                log(&mut batch, "End Definitions".to_string());
+            }
+            Command::Timestamp(time) => {
+                log(&mut batch, format!("Timestamp: {:?}", time));
+                // An explicit #0 after $dumpvars is already listed
+                if current_time != Some(time) {
+                    batch.timestamps.push(time);
+                }
+                current_time = Some(time);
+            }
+            Command::ChangeScalar(id, value) => {
+                log(
+                    &mut batch,
+                    format!("{:?} changed to {:?}", id.to_string(), value),
+                );
+                batch.changes.push((id, now, SignalValue::Scalar(value)));
+            }
+            Command::ChangeVector(id, value) => {
+                log(
+                    &mut batch,
+                    format!("{:?} changed to {:?}", id.to_string(), value.to_string()),
+                );
+                let value = match widths.get(&id) {
+                    Some(width) => extend_vector(value, *width),
+                    None => value,
+                };
+                batch.changes.push((id, now, SignalValue::Vector(value)));
+            }
+            Command::ChangeReal(id, value) => {
+                log(
+                    &mut batch,
+                    format!("{:?} changed to {:?}", id.to_string(), value),
+                );
+                batch.changes.push((id, now, SignalValue::Real(value)));
+            }
+            Command::ChangeString(id, value) => {
+                log(
+                    &mut batch,
+                    format!("{:?} changed to {:?}", id.to_string(), value),
+                );
+                batch.changes.push((id, now, SignalValue::String(value)));
+            }
+            _ => {}
+        }
+
+        commands += 1;
+        if commands.is_multiple_of(BATCH_COMMANDS) {
+            let batch = tracked(&mut parser, &mut indexed, std::mem::take(&mut batch));
+            sent = batch.bytes;
+            if sender.send(Message::Batch(batch)).is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    let _ = sender.send(Message::Batch(tracked(&mut parser, &mut indexed, batch)));
+    Ok(())
+}
+
+// Note in `batch` how far the parser has read, with the line index entries
+// past the `indexed` ones already sent
+fn tracked<R: BufRead>(
+    parser: &mut Parser<TrackedReader<R>>,
+    indexed: &mut usize,
+    mut batch: Batch,
+) -> Batch {
+    let reader = parser.reader();
+    batch.bytes = reader.bytes;
+    batch.lines = reader.line_count();
+    batch.line_starts = reader.line_starts[*indexed..].to_vec();
+    *indexed = reader.line_starts.len();
+    if let Some(text) = reader.text.as_mut() {
+        batch.text = String::from_utf8_lossy(&std::mem::take(text)).into_owned();
+    }
+    batch
+}
+
+// Text of a VCD document, decompressed when it starts like a gzip or zstd stream
+struct Input {
+    reader: Box<dyn BufRead + Send>,
+    // Size of the file, 0 when unknown or compressed
+    size: u64,
+    // Whether the text can be read again from the file, as it's neither stdin nor compressed
+    rereadable: bool,
+}
+
+impl Input {
+    fn open(path: &Path) -> io::Result<Input> {
+        let stdin = path == Path::new("-");
+        let (mut reader, size): (Box<dyn BufRead + Send>, u64) = if stdin {
+            (Box::new(BufReader::new(io::stdin())), 0)
+        } else {
+            let file = File::open(path)?;
+            let size = file.metadata()?.len();
+            (Box::new(BufReader::new(file)), size)
+        };
+
+        let magic = reader.fill_buf()?;
+        // FST files are read whole from their blocks, which needs a file to seek in
+        if magic.starts_with(&FST_MAGIC) {
+            return Err(io::Error::other(
+                "this is an FST file, which is only read from a path ending in .fst",
+            ));
+        }
+        let (gzip, zstd) = (
+            magic.starts_with(&GZIP_MAGIC),
+            magic.starts_with(&ZSTD_MAGIC),
+        );
+        if gzip {
+            reader = Box::new(BufReader::new(MultiGzDecoder::new(reader)));
+        } else if zstd {
+            reader = Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?));
+        }
+
+        let compressed = gzip || zstd;
+        Ok(Input {
+            reader,
+            size: if compressed { 0 } else { size },
+            rereadable: !stdin && !compressed,
+        })
+    }
+}
+
+// Size in bytes with a binary unit, e.g. 1.5 GiB
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+// Left-extend a vector to the declared width of its signal, as `b10` for a 4-bit
+// signal means 0010. Vectors starting with x or z are extended with that value
+fn extend_vector(vector: Vector, width: usize) -> Vector {
+    let mut bits: Vec<Value> = vector.into();
+
+    if bits.len() < width {
+        let fill = match bits.first() {
+            Some(Value::X) => Value::X,
+            Some(Value::Z) => Value::Z,
+            _ => Value::V0,
+        };
+        let mut extended = vec![fill; width - bits.len()];
+        extended.append(&mut bits);
+        bits = extended;
+    } else if bits.len() > width {
+        bits.drain(..bits.len() - width);
+    }
+
+    bits.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_arrive_in_batches_while_the_file_is_read() {
+        let mut text = String::from(
+            "$timescale 1ns $end\n$var wire 1 ! clk $end\n$var wire 4 \" n $end\n$enddefinitions $end\n",
+        );
+        for i in 0..BATCH_COMMANDS as u64 {
+            text.push_str(&format!("#{}\n{}!\nb{:b} \"\n", i * 5, i % 2, i % 16));
+        }
+        let path = std::env::temp_dir().join(format!("waveplot-loader-{}.vcd", std::process::id()));
+        std::fs::write(&path, &text).unwrap();
+
+        // The header is read up front, the changes as they arrive
+        let (mut waveform, mut loader) = Loader::open(&path).unwrap();
+        assert_eq!(waveform.signals.len(), 2);
+        assert!(waveform.timestamps.is_empty());
+        while waveform.timestamps.is_empty() {
+            loader.wait(&mut waveform, Duration::from_millis(10));
+        }
+        assert!(loader.read > 0);
+
+        loader.finish(&mut waveform).unwrap();
+        assert!(loader.done);
+        assert_eq!(loader.progress(), 1.0);
+        assert_eq!(waveform.last_timestamp(), (BATCH_COMMANDS as u64 - 1) * 5);
+        let n = &waveform.signals[1];
+        assert_eq!(waveform.signal_changes(n).len(), BATCH_COMMANDS);
+        assert_eq!(
+            waveform.value_at(n, 10),
+            Some(&SignalValue::Vector("0010".parse().unwrap()))
+        );
+        assert_eq!(waveform.source.line_count(), text.lines().count());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn compressed_files_are_decoded() {
+        let text =
+            "$timescale 1ns $end\n$var wire 1 ! clk $end\n$enddefinitions $end\n#0\n0!\n#5\n1!\n";
+        let path =
+            std::env::temp_dir().join(format!("waveplot-gzip-{}.vcd.gz", std::process::id()));
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        std::io::Write::write_all(&mut encoder, text.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        // The decoded text is kept for the VCD Code tab, as it can't be read again
+        let waveform = Waveform::load(&path).unwrap();
+        assert_eq!(waveform.last_timestamp(), 5);
+        assert_eq!(waveform.source.line_count(), text.lines().count());
+        assert_eq!(waveform.source.lines(3, 2).unwrap(), ["#0", "0!"]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn followed_files_are_read_as_they_grow() {
+        let path =
+            std::env::temp_dir().join(format!("waveplot-follow-grow-{}.vcd", std::process::id()));
+        std::fs::write(&path, "$timescale 1ns $end\n$var wire 1 ! clk $end\n").unwrap();
+        let append = |text: &str| {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            std::io::Write::write_all(&mut file, text.as_bytes()).unwrap();
+        };
+        let wait_for = |loader: &mut Loader, waveform: &mut Waveform, last: u64| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while waveform.last_timestamp() < last && Instant::now() < deadline {
+                loader.wait(waveform, Duration::from_millis(10));
+            }
+            assert_eq!(waveform.last_timestamp(), last);
+        };
+
+        // The header is waited for, and the changes are read up to the last whole line
+        let opened = {
+            let path = path.clone();
+            thread::spawn(move || Loader::follow(&path).unwrap())
+        };
+        thread::sleep(FOLLOW_POLL);
+        append("$enddefinitions $end\n#0\n0!\n#5\n1!\n#1");
+        let (mut waveform, mut loader) = opened.join().unwrap();
+        wait_for(&mut loader, &mut waveform, 5);
+
+        append("0\n0!\n#15\n1!\n");
+        wait_for(&mut loader, &mut waveform, 15);
+        assert!(!loader.done);
+        let clk = &waveform.signals[0];
+        assert_eq!(waveform.signal_changes(clk).len(), 4);
+        assert_eq!(
+            waveform.value_at(clk, 12),
+            Some(&SignalValue::Scalar(Value::V0))
+        );
+
+        // Truncating the file stops the reading
+        std::fs::write(&path, "").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !loader.done && Instant::now() < deadline {
+            loader.wait(&mut waveform, Duration::from_millis(10));
+        }
+        assert_eq!(loader.error.as_deref(), Some("the file was truncated"));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn only_plain_files_are_followed() {
+        let path =
+            std::env::temp_dir().join(format!("waveplot-follow-gzip-{}.vcd", std::process::id()));
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gzip, b"$timescale 1ns $end\n").unwrap();
+        std::fs::write(&path, gzip.finish().unwrap()).unwrap();
+
+        let err = Loader::follow(&path).err().unwrap().to_string();
+        assert!(err.ends_with("only plain VCD files can be followed"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
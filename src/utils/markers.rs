@@ -0,0 +1,129 @@
+use vcd::TimescaleUnit;
+
+use super::ruler::format_time;
+
+// Named times dropped on the Plot tab, measured against a reference marker
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Markers {
+    // (name, time), sorted by time
+    pub markers: Vec<(char, u64)>,
+    // Name of the marker every delta is measured from
+    pub reference: Option<char>,
+}
+
+impl Markers {
+    // Drop a marker named with the first free letter, None when every letter is taken
+    pub fn add(&mut self, time: u64) -> Option<char> {
+        let name = ('A'..='Z').find(|name| self.markers.iter().all(|(used, _)| used != name))?;
+
+        let index = self.markers.partition_point(|(_, marker)| *marker <= time);
+        self.markers.insert(index, (name, time));
+        if self.reference.is_none() {
+            self.reference = Some(name);
+        }
+
+        Some(name)
+    }
+
+    // Marker closest to `time`, the earlier one on a tie
+    pub fn nearest(&self, time: u64) -> Option<(char, u64)> {
+        self.markers
+            .iter()
+            .min_by_key(|(_, marker)| marker.abs_diff(time))
+            .copied()
+    }
+
+    pub fn remove_nearest(&mut self, time: u64) {
+        if let Some((name, _)) = self.nearest(time) {
+            self.markers.retain(|(marker, _)| *marker != name);
+            if self.reference == Some(name) {
+                self.reference = self.markers.first().map(|(name, _)| *name);
+            }
+        }
+    }
+
+    // Measure from the next marker in time order
+    pub fn next_reference(&mut self) {
+        let index = self
+            .markers
+            .iter()
+            .position(|(name, _)| Some(*name) == self.reference);
+        self.reference = match index {
+            Some(index) => self.markers.get((index + 1) % self.markers.len()),
+            None => self.markers.first(),
+        }
+        .map(|(name, _)| *name);
+    }
+
+    fn reference_time(&self) -> Option<u64> {
+        self.markers
+            .iter()
+            .find(|(name, _)| Some(*name) == self.reference)
+            .map(|(_, time)| *time)
+    }
+
+    // Every marker with its time and its delta from the reference marker
+    pub fn describe(&self, timescale: Option<(u32, TimescaleUnit)>) -> Vec<String> {
+        let reference = self.reference_time();
+
+        self.markers
+            .iter()
+            .map(|(name, time)| match reference {
+                Some(reference) if Some(*name) != self.reference => format!(
+                    "{} {} (Δ{} {})",
+                    name,
+                    format_time(*time, timescale),
+                    self.reference.unwrap_or_default(),
+                    format_delta(reference, *time, timescale)
+                ),
+                _ => format!("{} {} (ref)", name, format_time(*time, timescale)),
+            })
+            .collect()
+    }
+
+    // Delta between the cursor and the marker nearest to it
+    pub fn describe_cursor(
+        &self,
+        cursor: u64,
+        timescale: Option<(u32, TimescaleUnit)>,
+    ) -> Option<String> {
+        self.nearest(cursor).map(|(name, time)| {
+            format!("cursor Δ{} {}", name, format_delta(time, cursor, timescale))
+        })
+    }
+}
+
+// Signed time from `from` to `to`, e.g. "+15 ns"
+pub fn format_delta(from: u64, to: u64, timescale: Option<(u32, TimescaleUnit)>) -> String {
+    let sign = if to < from { "-" } else { "+" };
+    format!("{}{}", sign, format_time(from.abs_diff(to), timescale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deltas_are_measured_from_the_reference() {
+        let timescale = Some((1, TimescaleUnit::NS));
+        let mut markers = Markers::default();
+
+        assert_eq!(markers.add(30), Some('A'));
+        assert_eq!(markers.add(10), Some('B'));
+        assert_eq!(
+            markers.describe(timescale),
+            vec!["B 10 ns (ΔA -20 ns)", "A 30 ns (ref)"]
+        );
+
+        markers.next_reference();
+        assert_eq!(markers.reference, Some('B'));
+        assert_eq!(
+            markers.describe_cursor(12, timescale),
+            Some(String::from("cursor ΔB +2 ns"))
+        );
+
+        markers.remove_nearest(12);
+        assert_eq!(markers.reference, Some('A'));
+        assert_eq!(markers.add(0), Some('B'));
+    }
+}
@@ -0,0 +1,358 @@
+use ratatui::{prelude::*, widgets::*};
+
+use vcd::VarType;
+
+use super::{
+    radix::Radix,
+    ruler::{format_time, time_column, Ruler, TimeMarker},
+    theme::{Palette, Theme},
+    trace::{fit_label, format_value, AnalogTrace, Interpolation, Trace},
+    waveform::Waveform,
+};
+
+// Rows taken by the cursor and marker labels and the time ruler
+pub const RULER_HEIGHT: u16 = 3;
+
+// Signals of a waveform stacked under a shared time ruler: names on the left,
+// values at the cursor next to them and the traces on the right. Drawn by the
+// Plot tab and by the headless render
+#[derive(Clone)]
+pub struct Plot<'a> {
+    waveform: &'a Waveform,
+    // Indexes into Waveform::signals, one row each
+    signals: &'a [usize],
+    start: u64,
+    end: u64,
+    // Per signal settings indexed like Waveform::signals, `radix` for the missing ones
+    radix: Radix,
+    radixes: &'a [Radix],
+    interpolations: &'a [Interpolation],
+    // First row drawn and the row highlighted, as indexes into `signals`
+    scroll: usize,
+    selected: Option<usize>,
+    signal_height: u16,
+    cursor: Option<u64>,
+    markers: &'a [(char, u64)],
+    // Times under the mouse while a window is dragged out
+    selection: Option<(u64, u64)>,
+    palette: Palette,
+}
+
+impl<'a> Plot<'a> {
+    pub fn new(waveform: &'a Waveform, signals: &'a [usize], start: u64, end: u64) -> Plot<'a> {
+        Plot {
+            waveform,
+            signals,
+            start,
+            end,
+            radix: Radix::Hexadecimal,
+            radixes: &[],
+            interpolations: &[],
+            scroll: 0,
+            selected: None,
+            signal_height: 3,
+            cursor: None,
+            markers: &[],
+            selection: None,
+            palette: Theme::Dark.palette(),
+        }
+    }
+
+    pub fn radix(mut self, radix: Radix) -> Plot<'a> {
+        self.radix = radix;
+        self
+    }
+
+    pub fn radixes(mut self, radixes: &'a [Radix]) -> Plot<'a> {
+        self.radixes = radixes;
+        self
+    }
+
+    pub fn interpolations(mut self, interpolations: &'a [Interpolation]) -> Plot<'a> {
+        self.interpolations = interpolations;
+        self
+    }
+
+    pub fn scroll(mut self, scroll: usize) -> Plot<'a> {
+        self.scroll = scroll;
+        self
+    }
+
+    pub fn selected(mut self, selected: usize) -> Plot<'a> {
+        self.selected = Some(selected);
+        self
+    }
+
+    pub fn signal_height(mut self, signal_height: u16) -> Plot<'a> {
+        self.signal_height = signal_height.max(1);
+        self
+    }
+
+    // Draw a line at `cursor` and list the values at that time
+    pub fn cursor(mut self, cursor: u64) -> Plot<'a> {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    pub fn markers(mut self, markers: &'a [(char, u64)]) -> Plot<'a> {
+        self.markers = markers;
+        self
+    }
+
+    pub fn selection(mut self, selection: Option<(u64, u64)>) -> Plot<'a> {
+        self.selection = selection;
+        self
+    }
+
+    pub fn palette(mut self, palette: Palette) -> Plot<'a> {
+        self.palette = palette;
+        self
+    }
+
+    fn signal_radix(&self, signal: usize) -> Radix {
+        self.radixes.get(signal).copied().unwrap_or(self.radix)
+    }
+
+    // Value of every signal at the cursor, empty without a cursor
+    fn values(&self) -> Vec<String> {
+        let Some(cursor) = self.cursor else {
+            return vec![String::new(); self.signals.len()];
+        };
+
+        self.signals
+            .iter()
+            .map(|index| {
+                let signal = &self.waveform.signals[*index];
+                match self.waveform.value_at(signal, cursor) {
+                    Some(value) => format_value(value, self.signal_radix(*index)),
+                    None => String::new(),
+                }
+            })
+            .collect()
+    }
+
+    // Name, value and trace columns of `area`. The value column is left out without a cursor
+    pub fn columns(&self, area: Rect) -> (Rect, Rect, Rect) {
+        self.layout(area, &self.values())
+    }
+
+    fn layout(&self, area: Rect, values: &[String]) -> (Rect, Rect, Rect) {
+        let longest_name = self
+            .signals
+            .iter()
+            .map(|signal| {
+                self.waveform
+                    .signal_path(&self.waveform.signals[*signal])
+                    .chars()
+                    .count()
+            })
+            .max()
+            .unwrap_or(0) as u16;
+        let name_width = (longest_name + 1).clamp(12, (area.width / 3).max(12));
+
+        let value_width = if self.cursor.is_some() {
+            let longest_value = values
+                .iter()
+                .map(|value| value.chars().count())
+                .max()
+                .unwrap_or(0) as u16;
+            (longest_value + 1).clamp(6, (area.width / 5).max(6))
+        } else {
+            0
+        };
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(name_width),
+                Constraint::Length(value_width),
+                Constraint::Min(0),
+            ])
+            .split(area);
+        (columns[0], columns[1], columns[2])
+    }
+
+    // How many signals fit below the ruler
+    pub fn visible_rows(&self, area: Rect) -> usize {
+        (area.height.saturating_sub(RULER_HEIGHT) / self.signal_height).max(1) as usize
+    }
+
+    // Rows needed to draw every signal
+    pub fn height(&self) -> usize {
+        RULER_HEIGHT as usize + self.signals.len() * self.signal_height as usize
+    }
+}
+
+impl Widget for Plot<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let waveform = self.waveform;
+        let (start, end) = (self.start, self.end);
+        let signal_height = self.signal_height;
+
+        let values = self.values();
+        let (name_column, value_column, wave_column) = self.layout(area, &values);
+
+        // The first row is left for the cursor and marker labels
+        let ruler_area = Rect {
+            y: wave_column.y + 1,
+            height: (RULER_HEIGHT - 1).min(wave_column.height.saturating_sub(1)),
+            ..wave_column
+        };
+        Ruler::new(start, end)
+            .timescale(waveform.timescale)
+            .render(ruler_area, buf);
+
+        let visible = self
+            .signals
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.visible_rows(area));
+        for (row, (index, signal_index)) in visible.enumerate() {
+            let y = area.y + RULER_HEIGHT + row as u16 * signal_height;
+            if y + signal_height > area.bottom() {
+                break;
+            }
+
+            let signal = &waveform.signals[*signal_index];
+            let radix = self.signal_radix(*signal_index);
+            let interpolation = self
+                .interpolations
+                .get(*signal_index)
+                .copied()
+                .unwrap_or(Interpolation::Step);
+            let is_real = signal.var_type == VarType::Real;
+
+            // Name on the middle row, with the radix or interpolation below it when there is room
+            let mut name_style = Style::default().fg(self.palette.name);
+            if self.selected == Some(index) {
+                name_style = name_style.add_modifier(Modifier::REVERSED);
+            }
+            let name = fit_label(
+                &waveform.signal_path(signal),
+                name_column.width.saturating_sub(1) as usize,
+            );
+            let setting = if is_real {
+                interpolation.name()
+            } else if signal.size > 1 && signal.var_type != VarType::String {
+                radix.name()
+            } else {
+                ""
+            };
+            let name_area = Rect {
+                y,
+                height: signal_height,
+                ..name_column
+            };
+            let mut name_lines = vec![Line::from(""); (signal_height as usize - 1) / 2];
+            name_lines.push(Line::from(Span::styled(name, name_style)));
+            name_lines.push(Line::from(Span::styled(
+                setting,
+                Style::default().fg(Color::Gray),
+            )));
+            Paragraph::new(name_lines).render(name_area, buf);
+
+            let value_area = Rect {
+                y: y + (signal_height - 1) / 2,
+                height: 1,
+                ..value_column
+            };
+            let value = fit_label(
+                &values[index],
+                value_column.width.saturating_sub(1) as usize,
+            );
+            Paragraph::new(Span::styled(value, Style::default().fg(self.palette.value)))
+                .render(value_area, buf);
+
+            // Vector and string signals are drawn as a bus with the value inside every
+            // segment, real signals as an analog line
+            let trace_area = Rect {
+                y,
+                height: signal_height,
+                ..wave_column
+            };
+            if is_real {
+                AnalogTrace::new(waveform.signal_changes(signal), start, end)
+                    .interpolation(interpolation)
+                    .summary(waveform.summary(signal))
+                    .palette(self.palette)
+                    .render(trace_area, buf);
+            } else {
+                Trace::new(waveform.signal_changes(signal), start, end)
+                    .radix(radix)
+                    .palette(self.palette)
+                    .render(trace_area, buf);
+            }
+        }
+
+        for (name, time) in self.markers.iter() {
+            TimeMarker::new(*time, start, end)
+                .label(name.to_string())
+                .color(self.palette.marker)
+                .render(wave_column, buf);
+        }
+        if let Some(cursor) = self.cursor {
+            TimeMarker::new(cursor, start, end)
+                .label(format_time(cursor, waveform.timescale))
+                .color(self.palette.cursor)
+                .render(wave_column, buf);
+        }
+
+        // Shade the window being dragged out with the mouse
+        if let Some((from, to)) = self.selection {
+            let left = time_column(from.min(to), start, end, wave_column.width);
+            let right = time_column(from.max(to), start, end, wave_column.width);
+            let selection_area = Rect {
+                x: wave_column.x + left,
+                width: (right - left + 1).min(wave_column.width - left),
+                ..wave_column
+            };
+            Block::default()
+                .style(Style::default().bg(Color::DarkGray))
+                .render(selection_area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::waveform::tests::load_fixture;
+
+    #[test]
+    fn rows_fit_below_the_ruler() {
+        let waveform = load_fixture("test6.vcd");
+        let signals: Vec<usize> = (0..waveform.signals.len()).collect();
+        let plot = Plot::new(&waveform, &signals, 0, 40).signal_height(3);
+
+        assert_eq!(plot.visible_rows(Rect::new(0, 0, 80, RULER_HEIGHT + 9)), 3);
+        assert_eq!(plot.visible_rows(Rect::new(0, 0, 80, RULER_HEIGHT + 11)), 3);
+        // Tiny terminals still show the selected signal
+        assert_eq!(plot.visible_rows(Rect::new(0, 0, 80, 1)), 1);
+    }
+
+    #[test]
+    fn rows_list_names_and_values_at_the_cursor() {
+        let waveform = load_fixture("test6.vcd");
+        let signals = [waveform.find_signal("dut.pc").unwrap()];
+        let plot = Plot::new(&waveform, &signals, 0, 40)
+            .signal_height(1)
+            .cursor(20);
+
+        let area = Rect::new(0, 0, 60, plot.height() as u16);
+        let (name_column, value_column, _) = plot.columns(area);
+        let mut buffer = Buffer::empty(area);
+        plot.render(area, &mut buffer);
+
+        let row: String = (0..area.width)
+            .map(|x| buffer.get(x, RULER_HEIGHT).symbol.clone())
+            .collect();
+        assert!(row.starts_with("cpu_tb.dut.pc "));
+        assert_eq!(
+            row[value_column.x as usize..value_column.right() as usize].trim(),
+            "01"
+        );
+        assert_eq!(name_column.width, 14);
+    }
+}
@@ -1,26 +1,106 @@
-use vcd::{Command, Parser, ScopeItem, Value};
-
-use std::{collections::HashMap, env, error::Error, fs, fs::File, io, io::BufReader, rc::Rc, vec};
+use std::{error::Error, fs, io, path::Path, time::Duration};
+
+use super::{
+    cli::View,
+    csv::CsvExport,
+    expression::Expression,
+    loader::{format_size, Loader},
+    markers::Markers,
+    plot::Plot,
+    radix::Radix,
+    ruler::format_time,
+    scope_tree::{ScopeTree, TreeRow},
+    search::{find_next, Query},
+    svg::SvgPlot,
+    theme::Palette,
+    trace::Interpolation,
+    waveform::{Edge, Waveform},
+    wavejson::WaveJson,
+};
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
-use ratatui::{prelude::*, widgets::*};
+use ratatui::{
+    prelude::*,
+    widgets::{
+        block::{Position, Title},
+        *,
+    },
+};
+
+// What the text typed at the Plot tab prompt is run as
+#[derive(Clone, Copy, PartialEq)]
+enum Prompt {
+    Search,
+    Expression,
+    // Path of the file the plotted signals are exported to
+    Export,
+}
+
+// Time between redraws while a file is being read
+const LOAD_REFRESH: Duration = Duration::from_millis(100);
+
+// Range of the rows taken by every signal on the Plot tab
+const MIN_SIGNAL_HEIGHT: u16 = 1;
+const MAX_SIGNAL_HEIGHT: u16 = 8;
 
 struct App<'a> {
     pub titles: Vec<&'a str>,
     pub index: usize,
     pub state: TableState,
     pub items_length: usize,
-    pub scroll_parser_tab: u16,
-    pub scroll_vcd_tab: u16,
+    // First line shown on the Parser and VCD Code tabs
+    pub scroll_parser_tab: usize,
+    pub scroll_vcd_tab: usize,
+    pub waveform: Waveform,
+    // Signals shown on the Plot tab, as indexes into waveform.signals
+    pub plotted: Vec<usize>,
+    pub selected_signal: usize,
+    // First plotted signal shown, and how many fit on the Plot tab
+    pub signal_scroll: usize,
+    pub visible_signals: usize,
+    pub signal_height: u16,
+    pub radixes: Vec<Radix>,
+    pub interpolations: Vec<Interpolation>,
+    pub tree: ScopeTree,
+    pub show_tree: bool,
+    pub tree_focus: bool,
+    // Time window shown on the Plot tab
+    pub view_start: u64,
+    pub view_end: u64,
+    // Where the traces were last drawn, to map mouse columns to times
+    pub wave_area: Rect,
+    // Times under the mouse when a drag started and where it is now
+    pub selection: Option<(u64, u64)>,
+    // Time whose values are listed next to the signal names
+    pub cursor: u64,
+    pub markers: Markers,
+    // Prompt open on the Plot tab with the text typed so far, and the last search run
+    pub prompt: Option<(Prompt, String)>,
+    pub last_search: String,
+    // Outcome of the last search, expression or export, shown until the next one
+    pub status: Option<String>,
+    pub palette: Palette,
+    // Reads the rest of the file while it is browsed, None once it is read
+    pub loader: Option<Loader>,
+    // Signals added from expressions, computed again as the loader adds changes
+    pub derived: Vec<(usize, Expression)>,
 }
 
 impl<'a> App<'a> {
-    fn new() -> App<'a> {
+    fn new(waveform: Waveform, view: View, loader: Option<Loader>) -> App<'a> {
+        let mut radixes = vec![Radix::Hexadecimal; waveform.signals.len()];
+        for signal in view.signals.iter() {
+            radixes[*signal] = view.radix;
+        }
+
         App {
             titles: vec!["Plot", "Parser", "Header", "VCD Code"],
             index: 0,
@@ -28,6 +108,29 @@ impl<'a> App<'a> {
             items_length: 0,
             scroll_parser_tab: 0,
             scroll_vcd_tab: 0,
+            plotted: view.signals,
+            selected_signal: 0,
+            signal_scroll: 0,
+            visible_signals: 1,
+            signal_height: 3,
+            radixes,
+            interpolations: vec![Interpolation::Step; waveform.signals.len()],
+            tree: ScopeTree::new(&waveform),
+            show_tree: false,
+            tree_focus: false,
+            view_start: view.start,
+            view_end: view.end,
+            wave_area: Rect::default(),
+            selection: None,
+            cursor: view.cursor.unwrap_or(view.start),
+            markers: view.markers,
+            prompt: None,
+            last_search: String::new(),
+            status: None,
+            palette: view.theme.palette(),
+            loader,
+            derived: Vec::new(),
+            waveform,
         }
     }
 
@@ -71,8 +174,408 @@ impl<'a> App<'a> {
         }
     }
 
+    pub fn next_signal(&mut self) {
+        if !self.plotted.is_empty() {
+            self.selected_signal = (self.selected_signal + 1) % self.plotted.len();
+        }
+        self.scroll_to_selected_signal();
+    }
+
+    pub fn previous_signal(&mut self) {
+        if self.selected_signal > 0 {
+            self.selected_signal -= 1;
+        } else if !self.plotted.is_empty() {
+            self.selected_signal = self.plotted.len() - 1;
+        }
+        self.scroll_to_selected_signal();
+    }
+
+    // Move the selection a page of signals down or up, scrolling along with it
+    pub fn next_signal_page(&mut self) {
+        self.selected_signal =
+            (self.selected_signal + self.visible_signals).min(self.plotted.len().saturating_sub(1));
+        self.scroll_to_selected_signal();
+    }
+
+    pub fn previous_signal_page(&mut self) {
+        self.selected_signal = self.selected_signal.saturating_sub(self.visible_signals);
+        self.scroll_to_selected_signal();
+    }
+
+    pub fn scroll_signals_down(&mut self) {
+        self.signal_scroll = (self.signal_scroll + 1).min(self.max_signal_scroll());
+    }
+
+    pub fn scroll_signals_up(&mut self) {
+        self.signal_scroll = self.signal_scroll.saturating_sub(1);
+    }
+
+    fn max_signal_scroll(&self) -> usize {
+        self.plotted.len().saturating_sub(self.visible_signals)
+    }
+
+    fn scroll_to_selected_signal(&mut self) {
+        if self.selected_signal < self.signal_scroll {
+            self.signal_scroll = self.selected_signal;
+        } else if self.selected_signal >= self.signal_scroll + self.visible_signals {
+            self.signal_scroll = self.selected_signal + 1 - self.visible_signals;
+        }
+    }
+
+    pub fn taller_signals(&mut self) {
+        self.signal_height = (self.signal_height + 1).min(MAX_SIGNAL_HEIGHT);
+    }
+
+    pub fn shorter_signals(&mut self) {
+        self.signal_height = (self.signal_height - 1).max(MIN_SIGNAL_HEIGHT);
+    }
+
+    pub fn next_radix(&mut self) {
+        if let Some(signal_index) = self.plotted.get(self.selected_signal) {
+            self.radixes[*signal_index] = self.radixes[*signal_index].next();
+        }
+    }
+
+    pub fn toggle_interpolation(&mut self) {
+        if let Some(signal_index) = self.plotted.get(self.selected_signal) {
+            self.interpolations[*signal_index] = self.interpolations[*signal_index].toggle();
+        }
+    }
+
+    // Show or hide the scope tree, focusing it when shown
+    pub fn toggle_tree(&mut self) {
+        self.show_tree = !self.show_tree;
+        self.tree_focus = self.show_tree;
+    }
+
+    pub fn toggle_tree_focus(&mut self) {
+        self.tree_focus = self.show_tree && !self.tree_focus;
+    }
+
+    pub fn next_tree_row(&mut self) {
+        self.tree.next(&self.waveform);
+    }
+
+    pub fn previous_tree_row(&mut self) {
+        self.tree.previous(&self.waveform);
+    }
+
+    // Expand or collapse the scope under the cursor, or add or remove the signal under it
+    pub fn activate_tree_row(&mut self) {
+        match self.tree.selected_row(&self.waveform) {
+            Some(TreeRow::Scope(scope)) => self.tree.toggle(scope),
+            Some(TreeRow::Signal(signal)) => {
+                if self.plotted.contains(&signal) {
+                    self.plotted.retain(|plotted| *plotted != signal);
+                    self.clamp_selected_signal();
+                } else {
+                    self.plotted.push(signal);
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Add every signal of the scope under the cursor to the Plot tab
+    pub fn add_tree_scope(&mut self) {
+        if let Some(scope) = self.tree.selected_scope(&self.waveform) {
+            for signal in self.waveform.scopes[scope].signals.iter() {
+                if !self.plotted.contains(signal) {
+                    self.plotted.push(*signal);
+                }
+            }
+        }
+    }
+
+    pub fn remove_signal(&mut self) {
+        if self.selected_signal < self.plotted.len() {
+            self.plotted.remove(self.selected_signal);
+            self.clamp_selected_signal();
+        }
+    }
+
+    fn clamp_selected_signal(&mut self) {
+        self.selected_signal = self
+            .selected_signal
+            .min(self.plotted.len().saturating_sub(1));
+    }
+
+    // Show the time window [start, end], kept inside the dump and at least one timestamp wide
+    fn set_view(&mut self, start: u64, end: u64) {
+        let last = self.waveform.last_timestamp();
+        let span = end.saturating_sub(start).clamp(1, last.max(1));
+        let start = start.min(last.saturating_sub(span));
+
+        self.view_start = start;
+        self.view_end = start + span;
+    }
+
+    // Halve the window, keeping `time` at the same column
+    pub fn zoom_in(&mut self, time: u64) {
+        let time = time.clamp(self.view_start, self.view_end);
+        let start = time - (time - self.view_start) / 2;
+        let end = time + (self.view_end - time) / 2;
+        if end > start {
+            self.set_view(start, end);
+        }
+    }
+
+    // Double the window, keeping `time` at the same column
+    pub fn zoom_out(&mut self, time: u64) {
+        let time = time.clamp(self.view_start, self.view_end);
+        let start = time.saturating_sub((time - self.view_start) * 2);
+        let end = time.saturating_add((self.view_end - time) * 2);
+        self.set_view(start, end);
+    }
+
+    pub fn zoom_fit(&mut self) {
+        self.set_view(0, self.waveform.last_timestamp());
+    }
+
+    pub fn view_center(&self) -> u64 {
+        self.view_start + (self.view_end - self.view_start) / 2
+    }
+
+    // Move the window by a quarter of its width
+    pub fn pan_left(&mut self) {
+        let step = ((self.view_end - self.view_start) / 4).max(1);
+        self.set_view(
+            self.view_start.saturating_sub(step),
+            self.view_end.saturating_sub(step),
+        );
+    }
+
+    pub fn pan_right(&mut self) {
+        let step = ((self.view_end - self.view_start) / 4).max(1);
+        self.set_view(self.view_start + step, self.view_end + step);
+    }
+
+    // Time under a terminal column, None outside the traces
+    pub fn time_at(&self, column: u16, row: u16) -> Option<u64> {
+        let area = self.wave_area;
+        if column < area.left()
+            || column >= area.right()
+            || row < area.top()
+            || row >= area.bottom()
+        {
+            return None;
+        }
+
+        let span = (self.view_end - self.view_start) as u128;
+        let offset = (column - area.left()) as u128 * span / area.width as u128;
+        Some(self.view_start + offset as u64)
+    }
+
+    // Zoom to the dragged selection once the mouse button is released,
+    // or move the cursor to where it was clicked
+    pub fn release_selection(&mut self) {
+        match self.selection.take() {
+            Some((from, to)) if from != to => self.set_view(from.min(to), from.max(to)),
+            Some((time, _)) => self.cursor = time,
+            None => {}
+        }
+    }
+
+    // Time covered by one column of the traces
+    fn column_time(&self) -> u64 {
+        ((self.view_end - self.view_start) / self.wave_area.width.max(1) as u64).max(1)
+    }
+
+    pub fn cursor_left(&mut self) {
+        let time = self.cursor.saturating_sub(self.column_time());
+        self.move_cursor(time);
+    }
+
+    pub fn cursor_right(&mut self) {
+        let time = (self.cursor + self.column_time()).min(self.waveform.last_timestamp());
+        self.move_cursor(time);
+    }
+
+    // Move the cursor to the next or previous edge of the selected signal
+    pub fn jump_to_edge(&mut self, forward: bool, edge: Edge) {
+        let Some(signal_index) = self.plotted.get(self.selected_signal) else {
+            return;
+        };
+        let signal = &self.waveform.signals[*signal_index];
+
+        let time = if forward {
+            self.waveform.next_edge(signal, self.cursor, edge)
+        } else {
+            self.waveform.previous_edge(signal, self.cursor, edge)
+        };
+        if let Some(time) = time {
+            self.move_cursor(time);
+        }
+    }
+
+    // Move the cursor to the nearest change of any signal shown on the Plot tab
+    pub fn jump_to_any_change(&mut self, forward: bool) {
+        let visible = self
+            .plotted
+            .iter()
+            .skip(self.signal_scroll)
+            .take(self.visible_signals)
+            .map(|signal_index| &self.waveform.signals[*signal_index]);
+
+        let time = if forward {
+            visible
+                .filter_map(|signal| self.waveform.next_edge(signal, self.cursor, Edge::Any))
+                .min()
+        } else {
+            visible
+                .filter_map(|signal| self.waveform.previous_edge(signal, self.cursor, Edge::Any))
+                .max()
+        };
+        if let Some(time) = time {
+            self.move_cursor(time);
+        }
+    }
+
+    // Open the search prompt with the last search, or an empty expression prompt
+    fn open_prompt(&mut self, prompt: Prompt) {
+        let text = match prompt {
+            Prompt::Search => self.last_search.clone(),
+            Prompt::Expression => String::new(),
+            Prompt::Export => String::from("waveplot.svg"),
+        };
+        self.prompt = Some((prompt, text));
+        self.status = None;
+    }
+
+    fn run_prompt(&mut self) {
+        match self.prompt.take() {
+            Some((Prompt::Search, text)) => self.run_search(text),
+            Some((Prompt::Expression, text)) => self.add_expression(&text),
+            Some((Prompt::Export, text)) => self.export(text.trim()),
+            None => {}
+        }
+    }
+
+    // Move the cursor to the next change matching a search
+    fn run_search(&mut self, text: String) {
+        self.last_search = text.clone();
+
+        let query = match Query::parse(&text) {
+            Ok(query) => query,
+            Err(err) => {
+                self.status = Some(format!("Invalid search: {}", err));
+                return;
+            }
+        };
+        let Some(signal_index) = self.waveform.find_signal(&query.signal) else {
+            self.status = Some(format!("No signal named '{}'", query.signal));
+            return;
+        };
+
+        let signal = &self.waveform.signals[signal_index];
+        let radix = self.radixes[signal_index];
+        match find_next(&self.waveform, signal, &query, self.cursor, radix) {
+            Some(time) => {
+                self.move_cursor(time);
+                if let Some(row) = self.plotted.iter().position(|s| *s == signal_index) {
+                    self.selected_signal = row;
+                    self.scroll_to_selected_signal();
+                }
+            }
+            None => self.status = Some(format!("No match for '{}' after the cursor", text)),
+        }
+    }
+
+    // Write the plotted signals over the time window, with the markers and the
+    // cursor, to a file in the format named by its extension
+    fn export(&mut self, path: &str) {
+        let result = match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("svg") => {
+                let svg = SvgPlot::new(
+                    &self.waveform,
+                    &self.plotted,
+                    self.view_start,
+                    self.view_end,
+                )
+                .radixes(&self.radixes)
+                .interpolations(&self.interpolations)
+                .cursor(Some(self.cursor))
+                .markers(&self.markers.markers)
+                .render();
+                fs::write(path, svg).map_err(|err| err.to_string())
+            }
+            Some("json") => {
+                let json = WaveJson::new(
+                    &self.waveform,
+                    &self.plotted,
+                    self.view_start,
+                    self.view_end,
+                )
+                .radixes(&self.radixes)
+                .render();
+                fs::write(path, json).map_err(|err| err.to_string())
+            }
+            Some("csv") => {
+                let csv = CsvExport::new(
+                    &self.waveform,
+                    &self.plotted,
+                    self.view_start,
+                    self.view_end,
+                )
+                .radixes(&self.radixes)
+                .render();
+                fs::write(path, csv).map_err(|err| err.to_string())
+            }
+            _ => Err(String::from(
+                "expected a file ending in .svg, .json or .csv",
+            )),
+        };
+
+        self.status = Some(match result {
+            Ok(()) => format!("Exported {} signals to {}", self.plotted.len(), path),
+            Err(err) => format!("Export to '{}' failed: {}", path, err),
+        });
+    }
+
+    // Plot a signal computed from an expression, optionally named as in `name = expression`
+    fn add_expression(&mut self, text: &str) {
+        let (name, expression) = match text.split_once('=') {
+            Some((name, expression))
+                if !expression.starts_with('=')
+                    && !name.trim().is_empty()
+                    && name.trim().chars().all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                (name.trim(), expression.trim())
+            }
+            _ => (text.trim(), text.trim()),
+        };
+
+        match Expression::parse(expression, &self.waveform) {
+            Ok(expression) => {
+                let changes = expression.changes(&self.waveform);
+                let signal = self.waveform.add_derived_signal(name, changes);
+                self.derived.push((signal, expression));
+                self.radixes.push(Radix::Hexadecimal);
+                self.interpolations.push(Interpolation::Step);
+                self.plotted.push(signal);
+                self.selected_signal = self.plotted.len() - 1;
+                self.scroll_to_selected_signal();
+            }
+            Err(err) => self.status = Some(format!("Invalid expression: {}", err)),
+        }
+    }
+
+    // Put the cursor at `time`, centring the window on it when it is out of view
+    pub fn move_cursor(&mut self, time: u64) {
+        self.cursor = time;
+        if time < self.view_start || time > self.view_end {
+            let half = (self.view_end - self.view_start) / 2;
+            self.set_view(
+                time.saturating_sub(half),
+                time.saturating_sub(half) + half * 2,
+            );
+        }
+    }
+
     pub fn scroll_parser_down(&mut self) {
-        self.scroll_parser_tab += 1;
+        if self.scroll_parser_tab + 1 < self.waveform.parser_log.len() {
+            self.scroll_parser_tab += 1;
+        }
     }
 
     pub fn scroll_parser_up(&mut self) {
@@ -82,7 +585,50 @@ impl<'a> App<'a> {
     }
 
     pub fn scroll_vcd_down(&mut self) {
-        self.scroll_vcd_tab += 1;
+        if self.scroll_vcd_tab + 1 < self.waveform.source.line_count() {
+            self.scroll_vcd_tab += 1;
+        }
+    }
+
+    // Add the changes read since the last call. A window showing everything read so
+    // far grows with the file, one that was zoomed or panned is left alone. When
+    // following a file, a zoomed window ending at the latest change scrolls with it
+    pub fn poll_loader(&mut self) {
+        let Some(loader) = self.loader.as_mut() else {
+            return;
+        };
+
+        let last = self.waveform.last_timestamp();
+        let changed = loader.poll(&mut self.waveform);
+        if changed {
+            // New changes happen at the last timestamp read before or later, so
+            // derived signals are only evaluated again from there
+            for (signal, expression) in self.derived.iter() {
+                let added = expression.changes_since(&self.waveform, last);
+                let code = self.waveform.signals[*signal].code;
+                let changes = self.waveform.changes.entry(code).or_default();
+                changes.truncate(changes.partition_point(|(time, _)| *time < last));
+                for (time, value) in added {
+                    if changes.last().map(|(_, last)| last) != Some(&value) {
+                        changes.push((time, value));
+                    }
+                }
+            }
+        }
+        if changed && self.view_end == last.max(self.view_start + 1) {
+            let latest = self.waveform.last_timestamp().max(self.view_start + 1);
+            if loader.following && self.view_start > 0 {
+                self.view_start += latest - self.view_end;
+            }
+            self.view_end = latest;
+        }
+
+        if loader.done {
+            if let Some(err) = loader.error.take() {
+                self.status = Some(format!("Reading stopped: {}", err));
+            }
+            self.loader = None;
+        }
     }
 
     pub fn scroll_vcd_up(&mut self) {
@@ -92,7 +638,13 @@ impl<'a> App<'a> {
     }
 }
 
-pub fn plot_handler() -> Result<(), Box<dyn Error>> {
+// Browse `waveform` in the terminal, starting with the signals and window of `view`,
+// while `loader` reads the rest of it
+pub fn plot_handler(
+    waveform: Waveform,
+    view: View,
+    loader: Option<Loader>,
+) -> Result<(), Box<dyn Error>> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -101,7 +653,7 @@ pub fn plot_handler() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let app = App::new();
+    let app = App::new(waveform, view, loader);
     let res = run_app(&mut terminal, app);
 
     // restore terminal
@@ -113,232 +665,237 @@ pub fn plot_handler() -> Result<(), Box<dyn Error>> {
     )?;
     terminal.show_cursor()?;
 
-    if let Err(err) = res {
-        println!("{err:?}");
-    }
-
-    Ok(())
+    Ok(res?)
 }
 
 // takes control of terminal and key events
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
     loop {
+        app.poll_loader();
         terminal.draw(|f| ui(f, &mut app))?;
 
+        // Redraw now and then while the file is being read, even without input
+        if app.loader.is_some() && !event::poll(LOAD_REFRESH)? {
+            continue;
+        }
+
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) if app.index == 0 => {
+                handle_plot_mouse(&mut app, mouse);
+                continue;
+            }
+            _ => continue,
+        };
+
         // Handle keyboard events
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                if key.code == KeyCode::Char('q') {
-                    return Ok(());
-                } else if key.code == KeyCode::Char('d') || key.code == KeyCode::Right {
-                    app.next();
-                } else if key.code == KeyCode::Char('a') || key.code == KeyCode::Left {
-                    app.previous();
+        if key.kind == KeyEventKind::Press {
+            // Keys typed at the prompt of the Plot tab
+            if let Some((_, text)) = app.prompt.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) => text.push(c),
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    KeyCode::Enter => app.run_prompt(),
+                    KeyCode::Esc => app.prompt = None,
+                    _ => {}
                 }
+                continue;
+            }
+
+            if key.code == KeyCode::Char('q') {
+                return Ok(());
+            } else if key.code == KeyCode::Char('d') || key.code == KeyCode::Right {
+                app.next();
+            } else if key.code == KeyCode::Char('a') || key.code == KeyCode::Left {
+                app.previous();
+            }
 
+            // Scope tree on the Plot and Header tabs
+            if app.index == 0 || app.index == 2 {
+                if key.code == KeyCode::Char('t') {
+                    app.toggle_tree();
+                    continue;
+                } else if key.code == KeyCode::Tab {
+                    app.toggle_tree_focus();
+                    continue;
+                }
+            }
+
+            if (app.index == 0 || app.index == 2) && app.tree_focus {
+                if key.code == KeyCode::Char('w') || key.code == KeyCode::Up {
+                    app.previous_tree_row();
+                } else if key.code == KeyCode::Char('s') || key.code == KeyCode::Down {
+                    app.next_tree_row();
+                } else if key.code == KeyCode::Enter {
+                    app.activate_tree_row();
+                } else if key.code == KeyCode::Char(' ') {
+                    app.add_tree_scope();
+                }
+            } else if app.index == 3 {
                 // VCD Code Tab (index 3)
-                if app.index == 3 {
-                    if key.code == KeyCode::Char('w') {
-                        app.scroll_vcd_up();
-                    } else if key.code == KeyCode::Char('s') {
-                        app.scroll_vcd_down();
-                    } else if key.code == KeyCode::Up {
-                        app.scroll_vcd_up();
-                    } else if key.code == KeyCode::Down {
-                        app.scroll_vcd_down();
-                    }
-                } else if app.index == 2 {
-                    // Header Tab (index 2)
-                    if key.code == KeyCode::Char('w') {
-                        app.previous_header_tab()
-                    } else if key.code == KeyCode::Char('s') {
-                        app.next_header_tab();
-                    } else if key.code == KeyCode::Up {
-                        app.previous_header_tab();
-                    } else if key.code == KeyCode::Down {
-                        app.next_header_tab();
-                    }
-                } else if app.index == 1 {
-                    // Parser Tab (index 1)
-                    if key.code == KeyCode::Char('w') {
-                        app.scroll_parser_up()
-                    } else if key.code == KeyCode::Char('s') {
-                        app.scroll_parser_down();
-                    } else if key.code == KeyCode::Up {
-                        app.scroll_parser_up()
-                    } else if key.code == KeyCode::Down {
-                        app.scroll_parser_down();
+                if key.code == KeyCode::Char('w') {
+                    app.scroll_vcd_up();
+                } else if key.code == KeyCode::Char('s') {
+                    app.scroll_vcd_down();
+                } else if key.code == KeyCode::Up {
+                    app.scroll_vcd_up();
+                } else if key.code == KeyCode::Down {
+                    app.scroll_vcd_down();
+                }
+            } else if app.index == 2 {
+                // Header Tab (index 2)
+                if key.code == KeyCode::Char('w') {
+                    app.previous_header_tab()
+                } else if key.code == KeyCode::Char('s') {
+                    app.next_header_tab();
+                } else if key.code == KeyCode::Up {
+                    app.previous_header_tab();
+                } else if key.code == KeyCode::Down {
+                    app.next_header_tab();
+                }
+            } else if app.index == 1 {
+                // Parser Tab (index 1)
+                if key.code == KeyCode::Char('w') {
+                    app.scroll_parser_up()
+                } else if key.code == KeyCode::Char('s') {
+                    app.scroll_parser_down();
+                } else if key.code == KeyCode::Up {
+                    app.scroll_parser_up()
+                } else if key.code == KeyCode::Down {
+                    app.scroll_parser_down();
+                }
+            } else if app.index == 0 {
+                // Plot Tab (index 0)
+                if key.code == KeyCode::Char('w') || key.code == KeyCode::Up {
+                    app.previous_signal();
+                } else if key.code == KeyCode::Char('s') || key.code == KeyCode::Down {
+                    app.next_signal();
+                } else if key.code == KeyCode::Char('r') {
+                    app.next_radix();
+                } else if key.code == KeyCode::Char('i') {
+                    app.toggle_interpolation();
+                } else if key.code == KeyCode::Char('x') || key.code == KeyCode::Delete {
+                    app.remove_signal();
+                } else if key.code == KeyCode::Char('+') || key.code == KeyCode::Char('=') {
+                    app.zoom_in(app.view_center());
+                } else if key.code == KeyCode::Char('-') {
+                    app.zoom_out(app.view_center());
+                } else if key.code == KeyCode::Char('h') {
+                    app.pan_left();
+                } else if key.code == KeyCode::Char('l') {
+                    app.pan_right();
+                } else if key.code == KeyCode::Char('f') {
+                    app.zoom_fit();
+                } else if key.code == KeyCode::PageDown {
+                    app.next_signal_page();
+                } else if key.code == KeyCode::PageUp {
+                    app.previous_signal_page();
+                } else if key.code == KeyCode::Char(']') {
+                    app.taller_signals();
+                } else if key.code == KeyCode::Char('[') {
+                    app.shorter_signals();
+                } else if key.code == KeyCode::Char(',') {
+                    app.cursor_left();
+                } else if key.code == KeyCode::Char('.') {
+                    app.cursor_right();
+                } else if key.code == KeyCode::Char('n') {
+                    app.jump_to_edge(true, Edge::Any);
+                } else if key.code == KeyCode::Char('N') {
+                    app.jump_to_edge(false, Edge::Any);
+                } else if key.code == KeyCode::Char('p') {
+                    app.jump_to_edge(true, Edge::Rising);
+                } else if key.code == KeyCode::Char('P') {
+                    app.jump_to_edge(false, Edge::Rising);
+                } else if key.code == KeyCode::Char('v') {
+                    app.jump_to_edge(true, Edge::Falling);
+                } else if key.code == KeyCode::Char('V') {
+                    app.jump_to_edge(false, Edge::Falling);
+                } else if key.code == KeyCode::Char('g') {
+                    app.jump_to_any_change(true);
+                } else if key.code == KeyCode::Char('G') {
+                    app.jump_to_any_change(false);
+                } else if key.code == KeyCode::Char('m') {
+                    if app.markers.add(app.cursor).is_none() {
+                        app.status =
+                            Some(String::from("All 26 markers are in use, remove one with M"));
                     }
+                } else if key.code == KeyCode::Char('M') {
+                    app.markers.remove_nearest(app.cursor);
+                } else if key.code == KeyCode::Char('\'') {
+                    app.markers.next_reference();
+                } else if key.code == KeyCode::Char('/') {
+                    app.open_prompt(Prompt::Search);
+                } else if key.code == KeyCode::Char(':') {
+                    app.open_prompt(Prompt::Expression);
+                } else if key.code == KeyCode::Char('X') {
+                    app.open_prompt(Prompt::Export);
                 }
             }
         }
     }
 }
 
-fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
-    let file_path = get_path();
-
-    let file = File::open(file_path.clone()).unwrap();
-
-    let mut parser = Parser::new(BufReader::new(file));
-
-    let header = parser.parse_header().unwrap();
-
-    let header_version = header.version.unwrap();
-    let header_date = header.date.unwrap();
-    let header_timescale = header.timescale.unwrap().0.to_string();
-    let header_timescale_unit = header.timescale.unwrap().1.to_string();
-
-    let mut variable_types = Vec::new();
-    let mut variable_sizes = Vec::new();
-    let mut variable_references = Vec::new();
-    // for temporarily holding variable indexes
-    let mut variable_indexes_ref = Vec::new();
-    let mut variable_indexes = Vec::new();
-    let mut variable_codes = Vec::new();
-    let mut variable_values = HashMap::new();
-    let mut variable_value_types = HashMap::new();
-    let mut variable_time_stamps = Vec::new();
-    let mut variable_graph_coordinates = HashMap::new();
-
-    let scope = header.items.iter().find_map(|f| {
-        if let ScopeItem::Scope(scope) = f {
-            Some(scope.clone())
-        } else {
-            None
-        }
-    });
+// Wheel zooms around the time under the mouse, or scrolls the signals when the
+// mouse is left of the traces. Dragging selects a window to zoom to, clicking
+// moves the cursor
+fn handle_plot_mouse(app: &mut App, mouse: MouseEvent) {
+    let time = app.time_at(mouse.column, mouse.row);
 
-    scope.clone().unwrap().items.iter().for_each(|x| match x {
-        ScopeItem::Var(v) => {
-            variable_types.push(v.var_type.to_string());
-            variable_sizes.push(v.size.to_string());
-            variable_references.push(v.reference.clone());
-            variable_indexes_ref.push(v.index);
-            variable_codes.push(v.code.to_string());
-            variable_values.insert(v.code.to_string(), Vec::<String>::new());
+    match mouse.kind {
+        MouseEventKind::ScrollUp if mouse.column < app.wave_area.left() => app.scroll_signals_up(),
+        MouseEventKind::ScrollDown if mouse.column < app.wave_area.left() => {
+            app.scroll_signals_down()
         }
-        _ => {}
-    });
-
-    variable_indexes_ref.iter().for_each(|x| {
-        if x.is_some() {
-            variable_indexes.push(x.unwrap().to_string());
-        } else {
-            variable_indexes.push("None".to_string());
-        }
-    });
-
-    let mut parse_line_by_line = Vec::new();
-
-    parser.for_each(|f| {
-        match f.unwrap() {
-            Command::Begin(id) => {
-                parse_line_by_line.push(format!("Begin: {:?}", id));
-            }
-            Command::ChangeReal(id, value) => {
-                parse_line_by_line.push(format!("{:?} changed to {:?}", id.to_string(), value));
-            }
-            Command::ChangeString(id, value) => {
-                parse_line_by_line.push(format!("{:?} changed to {:?}", id.to_string(), value));
-            }
-            Command::Comment(comment) => {
-                parse_line_by_line.push(format!("Comment: {:?}", comment));
-            }
-            Command::Date(date) => {
-                parse_line_by_line.push(format!("Date: {:?}", date));
-            }
-            Command::End(id) => {
-                parse_line_by_line.push(format!("End: {:?}", id));
-            }
-            Command::ChangeVector(id, value) => {
-                parse_line_by_line.push(format!(
-                    "{:?} changed to {:?}",
-                    id.to_string(),
-                    value.to_string()
-                ));
-            }
-            Command::Enddefinitions => {
-                parse_line_by_line.push(format!("End Definitions"));
-            }
-            Command::ScopeDef(scope_type, name) => {
-                parse_line_by_line.push(format!("Scope Def: {:?} {:?}", scope_type, name));
-            }
-            Command::Timescale(time, unit) => {
-                parse_line_by_line.push(format!("Timescale: {:?} {:?}", time, unit));
-            }
-            Command::Version(version) => {
-                parse_line_by_line.push(format!("Version: {:?}", version));
-            }
-            Command::Upscope => {
-                parse_line_by_line.push(format!("Upscope"));
-            }
-            Command::ChangeScalar(id, value) => {
-                parse_line_by_line.push(format!("{:?} changed to {:?}", id.to_string(), value));
-                if value == Value::V0 {
-                    variable_value_types.insert(id.to_string(), "0".to_string());
-                } else if value == Value::V1 {
-                    variable_value_types.insert(id.to_string(), "1".to_string());
-                } else if value == Value::X {
-                    variable_value_types.insert(id.to_string(), "X".to_string());
-                } else if value == Value::Z {
-                    variable_value_types.insert(id.to_string(), "Z".to_string());
-                } else {
-                    variable_value_types.insert(id.to_string(), "U".to_string());
-                }
-                variable_values
-                    .get_mut(&id.to_string())
-                    .unwrap()
-                    .push(value.to_string());
-            }
-            Command::Timestamp(time) => {
-                parse_line_by_line.push(format!("Timestamp: {:?}", time));
-                variable_time_stamps.push(time);
-            }
-            _ => {
-                // println!("Something else");
-            }
+        MouseEventKind::ScrollUp => app.zoom_in(time.unwrap_or(app.view_center())),
+        MouseEventKind::ScrollDown => app.zoom_out(time.unwrap_or(app.view_center())),
+        MouseEventKind::Down(MouseButton::Left) => {
+            app.selection = time.map(|time| (time, time));
         }
-    });
-
-    // Check if the variable is a 0 or 1 and if it is, add it to the variable_graph_coordinates
-    // And fill its values with respect to timestamps tuple of (timestamp, value)
-    variable_value_types.iter().for_each(|v| {
-        if v.1 == "0" || v.1 == "1" {
-            // fetch its values from variable_values
-            variable_graph_coordinates.insert(v.0, Vec::<(u64, u64)>::new());
-
-            for (index, element) in variable_values.get(v.0).unwrap().iter().enumerate() {
-                if element == "0" || element == "1" {
-                    variable_graph_coordinates.get_mut(v.0).unwrap().push((
-                        variable_time_stamps[index],
-                        element.to_string().parse::<u64>().unwrap(),
-                    ));
-                }
+        MouseEventKind::Drag(MouseButton::Left) => {
+            // Clamp to the edges when the mouse leaves the traces
+            if let Some((from, _)) = app.selection {
+                let area = app.wave_area;
+                let column = mouse
+                    .column
+                    .clamp(area.left(), area.right().saturating_sub(1));
+                let to = app.time_at(column, area.top()).unwrap_or(from);
+                app.selection = Some((from, to));
             }
         }
-    });
+        MouseEventKind::Up(MouseButton::Left) => app.release_selection(),
+        _ => {}
+    }
+}
 
-    // Making sure that values are filled for every timestamp
-    // If not, fill it with the last value
-    // Ex: (0, 1), (1, 1) will be converted to (0, 1), (1, 1), (2, 1) (3,1) etc. [3 is the last timestamp]
-    variable_graph_coordinates.iter_mut().for_each(|v| {
-        if variable_time_stamps.len() > v.1.len() {
-            for index in v.1.len()..variable_time_stamps.len() {
-                v.1.push((variable_time_stamps[index], v.1[v.1.len() - 1].1));
-            }
-        }
-    });
+// Selection and first signal shown once `visible` of `count` signals fit on a page,
+// keeping the selection on the page as the terminal is resized or signals removed
+fn fit_page(count: usize, visible: usize, selected: usize, scroll: usize) -> (usize, usize) {
+    let selected = selected.min(count.saturating_sub(1));
+    let scroll = scroll
+        .min(count.saturating_sub(visible))
+        .max((selected + 1).saturating_sub(visible))
+        .min(selected);
+    (selected, scroll)
+}
+
+fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let waveform = &app.waveform;
+
+    let header_version = waveform.version.clone().unwrap_or_default();
+    let header_date = waveform.date.clone().unwrap_or_default();
+    let (header_timescale, header_timescale_unit) = match waveform.timescale {
+        Some((time, unit)) => (time.to_string(), unit.to_string()),
+        None => (String::new(), String::new()),
+    };
 
     let size = f.size();
 
     // Make 2 chunks, one for the tabs and one for the content
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-                Constraint::Ratio(2, 10),
-                Constraint::Ratio(8, 10)
-            ])
+        .constraints([Constraint::Ratio(2, 10), Constraint::Ratio(8, 10)])
         .split(size);
 
     let block = Block::default();
@@ -372,151 +929,187 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         );
     f.render_widget(tabs, chunks[0]);
 
+    // Progress of the file still being read, below the tab names
+    if let Some(loader) = &app.loader {
+        if chunks[0].height > 3 {
+            let gauge_area = Rect {
+                x: chunks[0].x + 1,
+                y: chunks[0].bottom() - 2,
+                width: chunks[0].width.saturating_sub(2),
+                height: 1,
+            };
+            let last = format_time(waveform.last_timestamp(), waveform.timescale);
+            // The size of a file still being written, of stdin or of the text of a
+            // compressed file isn't known
+            let label = if loader.following || loader.total == 0 {
+                format!(
+                    "{}, {} read up to {}",
+                    if loader.following {
+                        "Following"
+                    } else {
+                        "Reading"
+                    },
+                    format_size(loader.read),
+                    last
+                )
+            } else {
+                format!(
+                    "Reading {:.0}% of {}, up to {}",
+                    loader.progress() * 100.0,
+                    format_size(loader.total),
+                    last
+                )
+            };
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(Color::Cyan).bg(Color::Black))
+                .ratio(loader.progress())
+                .label(label);
+            f.render_widget(gauge, gauge_area);
+        }
+    }
+
     if app.index == 0 {
         // Plot Tab (index 0)
-        let number_of_graphs = variable_graph_coordinates.len();
-
-        let mut variable_graphs_converted_coordinates = Vec::new();
-
-        // Convert the coordinates to f64 since the chart component only accepts f64
-        variable_graph_coordinates.iter().for_each(|(key, value)| {
-            let converted_data: Vec<(f64, f64)> =
-                value.iter().map(|(a, b)| (*a as f64, *b as f64)).collect();
-
-            variable_graphs_converted_coordinates.push(converted_data);
-        });
-
-        // Make 3 columns
-        let outer_layout_constraints = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(
-                [
-                    Constraint::Ratio(1, 3),
-                    Constraint::Ratio(1, 3),
-                    Constraint::Ratio(1, 3),
-                ]
-                .as_ref(),
-            )
-            .split(chunks[1]);
-
-        let mut inner_chunks_constraint = Vec::new();
-
-        let multiple_of_three = number_of_graphs / 3;
+        // The scope tree takes the left quarter when shown
+        let mut plot_area = chunks[1];
+        if app.show_tree {
+            let tree_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
+                .split(chunks[1]);
+            app.tree
+                .render(f, tree_chunks[0], waveform, &app.plotted, app.tree_focus);
+            plot_area = tree_chunks[1];
+        }
 
-        // If the number of graphs is less than 3, then make it 100% of the screen
-        // If the number of graphs >2 <6, then make it 50% of the screen
-        // If the number of graphs >5 <9, use the same logic as above
-        match multiple_of_three {
-            0 => inner_chunks_constraint.push(Constraint::Percentage(100)),
-            1 => {
-                inner_chunks_constraint.push(Constraint::Percentage(50));
-                inner_chunks_constraint.push(Constraint::Percentage(50));
+        // One row per signal under a shared time ruler, names on the left
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Blue))
+            .title(vec![
+                Span::styled(
+                    format!(
+                        "Waveforms {} - {}, cursor at {}",
+                        format_time(app.view_start, waveform.timescale),
+                        format_time(app.view_end, waveform.timescale),
+                        format_time(app.cursor, waveform.timescale)
+                    ),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    " ('w'/'s' select, 'r' radix, 'i' interpolation, 'x' remove, 'X' export, \
+                     'PgUp'/'PgDn' scroll, '['/']' height)",
+                    Style::default(),
+                ),
+            ])
+            // Time navigation keys along the bottom border
+            .title(
+                Title::from(
+                    " '+'/'-' or wheel zoom, 'h'/'l' pan, 'f' fit, drag to zoom in, \
+                     ','/'.' or click cursor, 'n'/'N' change, 'p'/'P' rising, \
+                     'v'/'V' falling, 'g'/'G' change of any signal, 'm' marker, '/' search, ':' expression ",
+                )
+                .position(Position::Bottom),
+            );
+        let mut inner = block.inner(plot_area);
+        f.render_widget(block, plot_area);
+
+        // Open prompt, or the outcome of the last search, expression or export, on the last row
+        let prompt = match (&app.prompt, &app.status) {
+            (Some((prompt, text)), _) => {
+                let label = match prompt {
+                    Prompt::Search => {
+                        "Search (e.g. data == 0x81, addr[7:4] == 4'hA, state ~ \"IDLE\"): "
+                    }
+                    Prompt::Expression => "Expression (e.g. en & ~underrun, sum = a + b): ",
+                    Prompt::Export => "Export to (.svg, .json for WaveDrom, .csv): ",
+                };
+                Some(Line::from(vec![
+                    Span::styled(label, Style::default().fg(Color::Gray)),
+                    Span::styled(format!("{}█", text), Style::default().fg(Color::White)),
+                ]))
             }
-            _ => {
-                for _ in 1..multiple_of_three {
-                    inner_chunks_constraint
-                        .push(Constraint::Percentage(100 / multiple_of_three as u16));
-                }
+            (None, Some(status)) => Some(Line::from(Span::styled(
+                status.clone(),
+                Style::default().fg(Color::Red),
+            ))),
+            (None, None) => None,
+        };
+        if let Some(prompt) = prompt {
+            if inner.height > 1 {
+                inner.height -= 1;
+                let prompt_area = Rect {
+                    y: inner.bottom(),
+                    height: 1,
+                    ..inner
+                };
+                f.render_widget(Paragraph::new(prompt), prompt_area);
             }
         }
 
-        // Three columns
-        let left_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(inner_chunks_constraint.as_ref())
-            .split(outer_layout_constraints[0]);
-
-        let middle_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(inner_chunks_constraint.as_ref())
-            .split(outer_layout_constraints[1]);
-
-        let right_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(inner_chunks_constraint.as_ref())
-            .split(outer_layout_constraints[2]);
-
-        for (index, value) in variable_graphs_converted_coordinates.iter().enumerate() {
-            let datasets_one = vec![Dataset::default()
-                .name("data")
-                .marker(symbols::Marker::Braille)
-                .style(Style::default().fg(Color::LightCyan))
-                .graph_type(GraphType::Line)
-                .data(&variable_graphs_converted_coordinates[index])];
-
-            let chart_one = Chart::new(datasets_one)
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            variable_graph_coordinates
-                                .keys()
-                                .nth(index)
-                                .unwrap()
-                                .to_string(),
-                            Style::default()
-                                .fg(Color::Cyan)
-                                .add_modifier(Modifier::ITALIC),
-                        ))
-                        .borders(Borders::ALL),
-                )
-                .x_axis(
-                    Axis::default()
-                        .title("Time Stamps")
-                        .style(Style::default())
-                        .bounds([
-                            variable_graphs_converted_coordinates[index][0].0,
-                            variable_graphs_converted_coordinates[index]
-                                [variable_graphs_converted_coordinates[index].len() - 1]
-                                .0,
-                        ])
-                        .labels(vec![
-                            Span::styled(
-                                variable_graphs_converted_coordinates[index][0]
-                                    .0
-                                    .to_string(),
-                                Style::default().bold(),
-                            ),
-                            Span::styled(
-                                variable_graphs_converted_coordinates[index]
-                                    [variable_graphs_converted_coordinates[index].len() - 1]
-                                    .0
-                                    .to_string(),
-                                Style::default().bold(),
-                            ),
-                        ]),
-                )
-                .y_axis(
-                    Axis::default()
-                        .title(Span::styled(
-                            variable_graph_coordinates
-                                .keys()
-                                .nth(index)
-                                .unwrap()
-                                .to_string(),
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::ITALIC),
-                        ))
-                        .style(Style::default())
-                        .bounds([0.0, 1.0])
-                        .labels(vec!["0".bold(), "1".bold()]),
-                );
-
-            let chunk_index = index / 3;
-            let chunk_offset = index % 3;
-
-            // Place the graphs in the respective chunks
-            let target_chunk = match chunk_offset {
-                0 => left_chunks.get(chunk_index),
-                1 => middle_chunks.get(chunk_index),
-                2 => right_chunks.get(chunk_index),
-                _ => None,
+        // Markers and their deltas along the bottom
+        if !app.markers.markers.is_empty() && inner.height > 1 {
+            let mut spans = vec![Span::styled(
+                "Markers ('m' add, 'M' remove, ''' reference): ",
+                Style::default().fg(Color::Gray),
+            )];
+            for text in app.markers.describe(waveform.timescale) {
+                spans.push(Span::styled(text, Style::default().fg(app.palette.marker)));
+                spans.push(Span::raw("  "));
+            }
+            if let Some(text) = app.markers.describe_cursor(app.cursor, waveform.timescale) {
+                spans.push(Span::styled(text, Style::default().fg(app.palette.cursor)));
+            }
+
+            inner.height -= 1;
+            let markers_area = Rect {
+                y: inner.bottom(),
+                height: 1,
+                ..inner
             };
+            f.render_widget(Paragraph::new(Line::from(spans)), markers_area);
+        }
 
-            if let Some(chunk) = target_chunk {
-                f.render_widget(chart_one, *chunk);
-            }
+        let plot = Plot::new(waveform, &app.plotted, app.view_start, app.view_end)
+            .radixes(&app.radixes)
+            .interpolations(&app.interpolations)
+            .signal_height(app.signal_height)
+            .selected(app.selected_signal)
+            .cursor(app.cursor)
+            .markers(&app.markers.markers)
+            .selection(app.selection)
+            .palette(app.palette);
+        app.wave_area = plot.columns(inner).2;
+
+        // Scroll through the signals that don't fit below the ruler
+        app.visible_signals = plot.visible_rows(inner);
+        (app.selected_signal, app.signal_scroll) = fit_page(
+            app.plotted.len(),
+            app.visible_signals,
+            app.selected_signal,
+            app.signal_scroll,
+        );
+        let max_scroll = app.max_signal_scroll();
+
+        if app.plotted.len() > app.visible_signals {
+            let mut scrollbar_state = ScrollbarState::default()
+                .content_length(max_scroll as u16 + 1)
+                .viewport_content_length(app.visible_signals as u16)
+                .position(app.signal_scroll as u16);
+            f.render_stateful_widget(
+                Scrollbar::default()
+                    .orientation(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(Some("↑"))
+                    .end_symbol(Some("↓")),
+                plot_area.inner(&Margin {
+                    vertical: 1,
+                    horizontal: 0,
+                }),
+                &mut scrollbar_state,
+            );
         }
+
+        f.render_widget(plot.scroll(app.signal_scroll), inner);
     } else if app.index == 2 {
         // Header Tab (index 2)
         let inside_chunk = Layout::default()
@@ -535,8 +1128,19 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
             )
             .split(inside_chunk[0]);
 
-        let header_scope_type = scope.clone().unwrap().scope_type.to_string();
-        let header_scope_identifier = scope.clone().unwrap().identifier.to_string();
+        // List the variables of the scope selected in the scope tree
+        let header_scope = app.tree.selected_scope(waveform);
+        let (header_scope_type, header_scope_identifier) = match header_scope {
+            Some(scope) => (
+                waveform.scopes[scope].scope_type.to_string(),
+                waveform.scope_path(scope),
+            ),
+            None => (String::new(), String::new()),
+        };
+        let header_signals = match header_scope {
+            Some(scope) => waveform.scopes[scope].signals.clone(),
+            None => waveform.top_signals.clone(),
+        };
 
         let header_version_block = Paragraph::new(vec![Line::from(vec![Span::styled(
             header_version,
@@ -610,23 +1214,24 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
 
         let header = Row::new(header_cells);
 
-        let mut row_data = vec![vec![".", ".", ".", ".", "."]];
-
-        let loop_length = variable_types.len();
-
-        app.items_length = loop_length + 1;
+        let mut row_data = vec![vec![".".to_string(); 5]];
 
         // Make table component acceptable data
-        for i in 0..loop_length {
+        for signal in header_signals.iter().map(|index| &waveform.signals[*index]) {
             row_data.push(vec![
-                &variable_types[i],
-                &variable_sizes[i],
-                &variable_references[i],
-                &variable_indexes[i],
-                &variable_codes[i],
+                signal.var_type.to_string(),
+                signal.size.to_string(),
+                signal.reference.clone(),
+                match signal.index {
+                    Some(index) => index.to_string(),
+                    None => "None".to_string(),
+                },
+                signal.code.to_string(),
             ])
         }
 
+        app.items_length = row_data.len();
+
         let rows = row_data.iter().map(|item| {
             let height = item
                 .iter()
@@ -634,7 +1239,7 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
                 .max()
                 .unwrap_or(0)
                 + 1;
-            let cells = item.iter().map(|c| Cell::from(*c));
+            let cells = item.iter().map(|c| Cell::from(c.as_str()));
             Row::new(cells).height(height as u16)
         });
 
@@ -667,21 +1272,27 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
                 Constraint::Percentage(20),
             ]);
 
-        f.render_stateful_widget(header_scope_block, inside_chunk[1], &mut app.state);
+        let mut table_area = inside_chunk[1];
+        if app.show_tree {
+            let tree_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .split(inside_chunk[1]);
+            app.tree
+                .render(f, tree_chunks[0], waveform, &app.plotted, app.tree_focus);
+            table_area = tree_chunks[1];
+        }
+
+        f.render_stateful_widget(header_scope_block, table_area, &mut app.state);
     } else if app.index == 3 {
         // VCD Code Tab (index 3)
-        // Extract the file content into a vector of strings
-        let file_content = match fs::read_to_string(file_path.clone()) {
-            Ok(content) => content,
-            Err(_) => String::from(""),
+        // Only the lines that fit are read, the file can be larger than memory
+        let height = chunks[1].height.saturating_sub(2) as usize;
+        let vcd_code_content = match waveform.source.lines(app.scroll_vcd_tab, height) {
+            Ok(lines) => lines.into_iter().map(Line::from).collect(),
+            Err(err) => vec![Line::from(format!("Can't read the file: {}", err))],
         };
 
-        // Extract the VCD code into a renderable format
-        let vcd_code_content = file_content
-            .lines()
-            .map(|l| Line::from(l))
-            .collect::<Vec<_>>();
-
         let vcd_code_tab = Paragraph::new(vcd_code_content)
             .style(Style::default().fg(Color::Gray))
             .block(
@@ -694,17 +1305,19 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
                     )),
             )
             .alignment(Alignment::Left)
-            .scroll((app.scroll_vcd_tab, 0))
             .wrap(Wrap { trim: true });
 
         f.render_widget(vcd_code_tab, chunks[1]);
     } else {
         // Parser Tab (index 1)
-        let mut parser_content = Vec::new();
-
-        parse_line_by_line.iter().for_each(|f| {
-            parser_content.push(Line::from(f.to_string()));
-        });
+        let height = chunks[1].height.saturating_sub(2) as usize;
+        let parser_content = waveform
+            .parser_log
+            .iter()
+            .skip(app.scroll_parser_tab)
+            .take(height)
+            .map(|line| Line::from(line.as_str()))
+            .collect::<Vec<_>>();
 
         let parser_block = Paragraph::new(parser_content)
             .style(Style::default().fg(Color::Gray))
@@ -718,25 +1331,155 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
                     )),
             )
             .alignment(Alignment::Left)
-            .scroll((app.scroll_parser_tab, 0))
             .wrap(Wrap { trim: true });
 
         f.render_widget(parser_block, chunks[1]);
     }
 }
 
-// Get the path passed to the program
-pub fn get_path() -> String {
-    let envs = env::args();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    use crate::utils::{
+        theme::Theme,
+        waveform::tests::{fixture_path, load_fixture},
+    };
+
+    fn view(signals: Vec<usize>, end: u64) -> View {
+        View {
+            signals,
+            start: 0,
+            end,
+            cursor: None,
+            markers: Markers::default(),
+            radix: Radix::Hexadecimal,
+            theme: Theme::default(),
+        }
+    }
 
-    for (i, env) in envs.enumerate() {
-        // Skip the first argument, which is the program name (waveplot or ./waveplot) in this case
-        if i == 0 {
-            continue;
-        } else {
-            return env.to_string();
+    fn plotting(signals: usize) -> App<'static> {
+        let waveform = load_fixture("test6.vcd");
+        let end = waveform.last_timestamp();
+        App::new(waveform, view((0..signals).collect(), end), None)
+    }
+
+    // What drawing the Plot tab does once `visible` signals fit
+    fn resize(app: &mut App, visible: usize) {
+        app.visible_signals = visible;
+        (app.selected_signal, app.signal_scroll) = fit_page(
+            app.plotted.len(),
+            visible,
+            app.selected_signal,
+            app.signal_scroll,
+        );
+    }
+
+    #[test]
+    fn pages_scroll_along_with_the_selection() {
+        let mut app = plotting(11);
+        resize(&mut app, 4);
+
+        app.next_signal_page();
+        assert_eq!((app.selected_signal, app.signal_scroll), (4, 1));
+        app.next_signal_page();
+        app.next_signal_page();
+        assert_eq!((app.selected_signal, app.signal_scroll), (10, 7));
+        for _ in 0..5 {
+            app.scroll_signals_down();
+        }
+        assert_eq!(app.signal_scroll, 7);
+        app.previous_signal_page();
+        assert_eq!((app.selected_signal, app.signal_scroll), (6, 6));
+    }
+
+    #[test]
+    fn scrolling_is_clamped_to_the_signals_and_the_page() {
+        // Fewer signals than fit on a page never scroll
+        let mut app = plotting(3);
+        resize(&mut app, 10);
+        app.scroll_signals_down();
+        app.next_signal_page();
+        assert_eq!((app.selected_signal, app.signal_scroll), (2, 0));
+
+        // A shrinking terminal keeps the selection on the page
+        let mut app = plotting(11);
+        resize(&mut app, 10);
+        app.selected_signal = 9;
+        resize(&mut app, 3);
+        assert_eq!(app.signal_scroll, 7);
+        resize(&mut app, 20);
+        assert_eq!(app.signal_scroll, 0);
+
+        app.plotted.truncate(4);
+        resize(&mut app, 2);
+        assert_eq!((app.selected_signal, app.signal_scroll), (3, 2));
+    }
+
+    #[test]
+    fn derived_signals_follow_the_loader() {
+        let (waveform, loader) = Loader::open(Path::new(&fixture_path("test6.vcd"))).unwrap();
+        let mut app = App::new(waveform, view(Vec::new(), 1), Some(loader));
+
+        // Added before any change is read
+        app.add_expression("clk & ~carry");
+        while app.loader.is_some() {
+            app.poll_loader();
+            std::thread::sleep(Duration::from_millis(1));
         }
+
+        let derived = &app.waveform.signals[app.plotted[0]];
+        let expected = Expression::parse("clk & ~carry", &app.waveform)
+            .unwrap()
+            .changes(&app.waveform);
+        assert_eq!(expected.len(), 5);
+        assert_eq!(app.waveform.signal_changes(derived), expected.as_slice());
     }
 
-    return String::from("");
+    #[test]
+    fn derived_signals_are_extended_batch_by_batch() {
+        let path =
+            std::env::temp_dir().join(format!("waveplot-derived-{}.vcd", std::process::id()));
+        let text = std::fs::read_to_string(fixture_path("test6.vcd")).unwrap();
+        let (header, dump) = text.split_at(text.find("$dumpvars").unwrap());
+        std::fs::write(&path, header).unwrap();
+
+        let (waveform, loader) = Loader::follow(&path).unwrap();
+        let mut app = App::new(waveform, view(Vec::new(), 1), Some(loader));
+        app.add_expression("clk & ~carry");
+        let derived = app.plotted[0];
+
+        // The dump is appended a timestamp at a time, each read as a batch of its own
+        let mut sections: Vec<String> = Vec::new();
+        for line in dump.split_inclusive('\n') {
+            match sections.last_mut() {
+                Some(section) if !line.starts_with('#') => section.push_str(line),
+                _ => sections.push(String::from(line)),
+            }
+        }
+        assert_eq!(sections.len(), 9);
+        for (read, section) in sections.iter().enumerate() {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            std::io::Write::write_all(&mut file, section.as_bytes()).unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while app.waveform.timestamps.len() <= read && Instant::now() < deadline {
+                app.poll_loader();
+                let expected = Expression::parse("clk & ~carry", &app.waveform)
+                    .unwrap()
+                    .changes(&app.waveform);
+                let changes = app.waveform.signal_changes(&app.waveform.signals[derived]);
+                assert_eq!(changes, expected.as_slice());
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            assert_eq!(app.waveform.timestamps.len(), read + 1);
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,219 @@
+use clap::ValueEnum;
+
+use vcd::Value;
+
+// How the value of a vector signal is printed, named as in Radix::name on the command line
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Radix {
+    #[value(name = "hex")]
+    Hexadecimal,
+    Unsigned,
+    Signed,
+    Binary,
+    Octal,
+    Ascii,
+}
+
+impl Radix {
+    // Cycle through the radixes in the order they are listed
+    pub fn next(self) -> Radix {
+        match self {
+            Radix::Hexadecimal => Radix::Unsigned,
+            Radix::Unsigned => Radix::Signed,
+            Radix::Signed => Radix::Binary,
+            Radix::Binary => Radix::Octal,
+            Radix::Octal => Radix::Ascii,
+            Radix::Ascii => Radix::Hexadecimal,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Radix::Hexadecimal => "hex",
+            Radix::Unsigned => "unsigned",
+            Radix::Signed => "signed",
+            Radix::Binary => "binary",
+            Radix::Octal => "octal",
+            Radix::Ascii => "ascii",
+        }
+    }
+
+    // Format the bits of a vector, most significant bit first.
+    // Digits made only of x (or z) bits print as 'x' ('z'), digits mixing
+    // known and unknown bits print as 'X' ('Z')
+    pub fn format(self, bits: &[Value]) -> String {
+        match self {
+            Radix::Binary => bits.iter().map(|bit| bit.to_string()).collect(),
+            Radix::Hexadecimal => format_groups(bits, 4),
+            Radix::Octal => format_groups(bits, 3),
+            Radix::Unsigned => match unknown_digit(bits) {
+                Some(digit) => digit.to_string(),
+                None => to_decimal(bits),
+            },
+            Radix::Signed => match unknown_digit(bits) {
+                Some(digit) => digit.to_string(),
+                None if bits.first() == Some(&Value::V1) => {
+                    format!("-{}", to_decimal(&twos_complement(bits)))
+                }
+                None => to_decimal(bits),
+            },
+            Radix::Ascii => format_ascii(bits),
+        }
+    }
+}
+
+// The digit printed for a group of bits containing x or z, None if every bit is 0 or 1
+fn unknown_digit(bits: &[Value]) -> Option<char> {
+    if bits.iter().all(|bit| *bit == Value::X) {
+        Some('x')
+    } else if bits.iter().all(|bit| *bit == Value::Z) {
+        Some('z')
+    } else if bits.contains(&Value::X) {
+        Some('X')
+    } else if bits.contains(&Value::Z) {
+        Some('Z')
+    } else {
+        None
+    }
+}
+
+// Format hexadecimal or octal digits, grouping bits from the least significant end
+fn format_groups(bits: &[Value], group_size: usize) -> String {
+    if bits.is_empty() {
+        return String::new();
+    }
+
+    let mut digits = Vec::new();
+    for group in bits.rchunks(group_size) {
+        let digit = match unknown_digit(group) {
+            Some(digit) => digit,
+            None => {
+                let value = group
+                    .iter()
+                    .fold(0, |value, bit| value << 1 | (*bit == Value::V1) as u32);
+                char::from_digit(value, 16).unwrap()
+            }
+        };
+        digits.push(digit);
+    }
+
+    digits.iter().rev().collect()
+}
+
+// Unsigned decimal of a vector of any width
+fn to_decimal(bits: &[Value]) -> String {
+    // Least significant decimal digit first
+    let mut digits = vec![0u8];
+
+    for bit in bits {
+        let mut carry = (*bit == Value::V1) as u8;
+        for digit in digits.iter_mut() {
+            let doubled = *digit * 2 + carry;
+            *digit = doubled % 10;
+            carry = doubled / 10;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+    }
+
+    digits
+        .iter()
+        .rev()
+        .map(|digit| char::from(b'0' + digit))
+        .collect()
+}
+
+// Negate a known vector: invert every bit and add one
+fn twos_complement(bits: &[Value]) -> Vec<Value> {
+    let mut negated: Vec<Value> = bits
+        .iter()
+        .map(|bit| {
+            if *bit == Value::V1 {
+                Value::V0
+            } else {
+                Value::V1
+            }
+        })
+        .collect();
+
+    for bit in negated.iter_mut().rev() {
+        if *bit == Value::V1 {
+            *bit = Value::V0;
+        } else {
+            *bit = Value::V1;
+            break;
+        }
+    }
+
+    negated
+}
+
+// One character per byte, skipping leading NUL bytes and printing unprintable bytes as '.'
+fn format_ascii(bits: &[Value]) -> String {
+    let mut text = String::new();
+
+    for byte in bits.rchunks(8).rev() {
+        let character = match unknown_digit(byte) {
+            Some(_) => '.',
+            None => {
+                let value = byte
+                    .iter()
+                    .fold(0u8, |value, bit| value << 1 | (*bit == Value::V1) as u8);
+                if value == 0 && text.is_empty() {
+                    continue;
+                } else if value.is_ascii_graphic() || value == b' ' {
+                    char::from(value)
+                } else {
+                    '.'
+                }
+            }
+        };
+        text.push(character);
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(text: &str) -> Vec<Value> {
+        text.chars()
+            .map(|c| c.to_string().parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn formats_known_values() {
+        let value = bits("10000001");
+
+        assert_eq!(Radix::Hexadecimal.format(&value), "81");
+        assert_eq!(Radix::Unsigned.format(&value), "129");
+        assert_eq!(Radix::Signed.format(&value), "-127");
+        assert_eq!(Radix::Binary.format(&value), "10000001");
+        assert_eq!(Radix::Octal.format(&value), "201");
+        assert_eq!(Radix::Ascii.format(&bits("0000000001000001")), "A");
+    }
+
+    #[test]
+    fn formats_unknown_digits() {
+        assert_eq!(Radix::Hexadecimal.format(&bits("xxxxxxxx")), "xx");
+        assert_eq!(Radix::Hexadecimal.format(&bits("0x010000")), "X0");
+        assert_eq!(Radix::Hexadecimal.format(&bits("zzzz0001")), "z1");
+        assert_eq!(Radix::Unsigned.format(&bits("xxxx")), "x");
+        assert_eq!(Radix::Signed.format(&bits("1z00")), "Z");
+    }
+
+    #[test]
+    fn formats_wide_decimals() {
+        let value = vec![Value::V1; 100];
+
+        assert_eq!(
+            Radix::Unsigned.format(&value),
+            "1267650600228229401496703205375"
+        );
+        assert_eq!(Radix::Signed.format(&value), "-1");
+    }
+}
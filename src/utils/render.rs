@@ -0,0 +1,253 @@
+use clap::ValueEnum;
+
+use ratatui::{
+    buffer::{Buffer, Cell},
+    prelude::*,
+    widgets::Widget,
+};
+
+use std::io::{self, IsTerminal};
+
+use super::{
+    cli::View,
+    plot::{Plot, RULER_HEIGHT},
+    waveform::Waveform,
+};
+
+// When the headless render is colored with ANSI escape codes
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ColorMode {
+    // Only when stdout is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Auto => io::stdout().is_terminal(),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+// Draw the signals of `view` as they appear on the Plot tab, `width` columns
+// wide with `signal_height` rows per signal. A buffer covers at most u16::MAX
+// cells, so long lists are drawn a page of signals per buffer, the ruler only
+// heading the first one
+pub fn render_view(
+    waveform: &Waveform,
+    view: &View,
+    width: u16,
+    signal_height: u16,
+) -> Vec<Buffer> {
+    let mut plot = Plot::new(waveform, &view.signals, view.start, view.end)
+        .radix(view.radix)
+        .signal_height(signal_height)
+        .markers(&view.markers.markers)
+        .palette(view.theme.palette());
+    if let Some(cursor) = view.cursor {
+        plot = plot.cursor(cursor);
+    }
+
+    let signal_height = signal_height.max(1) as usize;
+    let page =
+        ((u16::MAX / width.max(1)).saturating_sub(RULER_HEIGHT) as usize / signal_height).max(1);
+    let height = plot.height();
+
+    (0..view.signals.len().max(1))
+        .step_by(page)
+        .map(|first| {
+            let rows =
+                (height - first * signal_height).min(RULER_HEIGHT as usize + page * signal_height);
+            let mut buffer = Buffer::empty(Rect::new(0, 0, width, rows as u16));
+            let area = buffer.area;
+            plot.clone().scroll(first).render(area, &mut buffer);
+
+            if first > 0 {
+                let ruler_cells = (RULER_HEIGHT * width) as usize;
+                buffer.content.drain(..ruler_cells);
+                buffer.area.y = RULER_HEIGHT;
+                buffer.area.height -= RULER_HEIGHT;
+            }
+            buffer
+        })
+        .collect()
+}
+
+// Text of a buffer, one line per row without trailing blanks. Styles are written
+// as ANSI escape codes when `color` is set, and drawing characters are replaced
+// by ASCII ones when `ascii` is set
+pub fn buffer_to_text(buffer: &Buffer, color: bool, ascii: bool) -> String {
+    let area = buffer.area;
+    let mut lines = Vec::new();
+
+    for y in area.top()..area.bottom() {
+        let mut line = String::new();
+        let mut current = Cell::default().style();
+        // Length of the line up to its last non blank cell
+        let mut end = 0;
+
+        for x in area.left()..area.right() {
+            let cell = buffer.get(x, y);
+            let style = cell.style();
+
+            if color && style != current {
+                line.push_str(&ansi_style(style));
+                current = style;
+            }
+            if ascii {
+                line.push(ascii_symbol(&cell.symbol));
+            } else {
+                line.push_str(&cell.symbol);
+            }
+            if cell.symbol != " " || style.bg.is_some_and(|bg| bg != Color::Reset) {
+                end = line.len();
+            }
+        }
+
+        line.truncate(end);
+        if line.contains('\x1b') {
+            line.push_str(&ansi_style(Cell::default().style()));
+        }
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+// Escape code (SGR) switching to `style` from any other style
+fn ansi_style(style: Style) -> String {
+    let mut codes = vec![String::from("0")];
+
+    if let Some(code) = style.fg.and_then(|fg| ansi_color(fg, 30)) {
+        codes.push(code);
+    }
+    if let Some(code) = style.bg.and_then(|bg| ansi_color(bg, 40)) {
+        codes.push(code);
+    }
+    if style.add_modifier.contains(Modifier::BOLD) {
+        codes.push(String::from("1"));
+    }
+    if style.add_modifier.contains(Modifier::REVERSED) {
+        codes.push(String::from("7"));
+    }
+
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+// Parameters of a color, with `base` 30 for the foreground and 40 for the background
+fn ansi_color(color: Color, base: u8) -> Option<String> {
+    let code = match color {
+        Color::Reset => return None,
+        Color::Black => base,
+        Color::Red => base + 1,
+        Color::Green => base + 2,
+        Color::Yellow => base + 3,
+        Color::Blue => base + 4,
+        Color::Magenta => base + 5,
+        Color::Cyan => base + 6,
+        Color::Gray => base + 7,
+        Color::DarkGray => base + 60,
+        Color::LightRed => base + 61,
+        Color::LightGreen => base + 62,
+        Color::LightYellow => base + 63,
+        Color::LightBlue => base + 64,
+        Color::LightMagenta => base + 65,
+        Color::LightCyan => base + 66,
+        Color::White => base + 67,
+        Color::Rgb(red, green, blue) => {
+            return Some(format!("{};2;{};{};{}", base + 8, red, green, blue))
+        }
+        Color::Indexed(index) => return Some(format!("{};5;{}", base + 8, index)),
+    };
+    Some(code.to_string())
+}
+
+// Closest ASCII character to a drawing symbol, '.' for the analog dots
+fn ascii_symbol(symbol: &str) -> char {
+    match symbol {
+        "─" | "▔" | "┄" => '-',
+        "▁" => '_',
+        "│" => '|',
+        "╱" => '/',
+        "╲" => '\\',
+        "╳" => 'X',
+        "┌" | "┐" | "└" | "┘" | "┬" | "┴" => '+',
+        "…" => '~',
+        "↑" => '^',
+        "↓" => 'v',
+        "█" => '#',
+        symbol => match symbol.chars().next() {
+            Some(c) if c.is_ascii() => c,
+            Some(_) => '.',
+            None => ' ',
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::{
+        markers::Markers, radix::Radix, theme::Theme, waveform::tests::load_fixture,
+    };
+
+    #[test]
+    fn buffers_are_printed_as_text() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 6, 2));
+        buffer.set_string(0, 0, "┌─┐", Style::default().fg(Color::Red));
+        buffer.set_string(1, 1, "▁▔", Style::default());
+
+        assert_eq!(buffer_to_text(&buffer, false, false), "┌─┐\n ▁▔");
+        assert_eq!(buffer_to_text(&buffer, false, true), "+-+\n _-");
+        assert_eq!(
+            buffer_to_text(&buffer, true, false),
+            "\x1b[0;31m┌─┐\x1b[0m\n ▁▔"
+        );
+    }
+
+    #[test]
+    fn long_lists_are_drawn_a_page_at_a_time() {
+        let waveform = load_fixture("test6.vcd");
+        // More rows than fit in a buffer 120 columns wide
+        let view = View {
+            signals: (0..400)
+                .map(|signal| signal % waveform.signals.len())
+                .collect(),
+            start: 0,
+            end: waveform.last_timestamp(),
+            cursor: None,
+            markers: Markers::default(),
+            radix: Radix::Hexadecimal,
+            theme: Theme::default(),
+        };
+
+        let buffers = render_view(&waveform, &view, 120, 2);
+        assert!(buffers.len() > 1);
+        let text: Vec<String> = buffers
+            .iter()
+            .map(|buffer| buffer_to_text(buffer, false, false))
+            .collect();
+        let lines: Vec<&str> = text.iter().flat_map(|text| text.lines()).collect();
+
+        assert_eq!(lines.len(), RULER_HEIGHT as usize + 400 * 2);
+        assert!(lines.iter().all(|line| line.chars().count() <= 120));
+        assert!(lines[1].contains("0 ns"));
+        // The ruler isn't repeated, every signal follows the one before it
+        let names: Vec<&str> = lines
+            .iter()
+            .skip(RULER_HEIGHT as usize)
+            .step_by(2)
+            .map(|line| line.split_whitespace().next().unwrap_or(""))
+            .collect();
+        assert_eq!(names.len(), 400);
+        assert_eq!(
+            names[399],
+            waveform.signal_path(&waveform.signals[399 % 11])
+        );
+    }
+}
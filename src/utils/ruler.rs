@@ -0,0 +1,275 @@
+use ratatui::{prelude::*, widgets::Widget};
+
+use vcd::TimescaleUnit;
+
+use super::trace::{fit_label, format_real};
+
+// Time axis shared by every trace of the Plot tab: tick labels on the first
+// row and tick marks on the second, using the same column mapping as Trace
+pub struct Ruler {
+    start: u64,
+    end: u64,
+    timescale: Option<(u32, TimescaleUnit)>,
+    style: Style,
+    label_style: Style,
+}
+
+impl Ruler {
+    pub fn new(start: u64, end: u64) -> Ruler {
+        Ruler {
+            start,
+            end,
+            timescale: None,
+            style: Style::default().fg(Color::Blue),
+            label_style: Style::default().fg(Color::Gray).bold(),
+        }
+    }
+
+    // Label the ticks in the $timescale units of the dump instead of raw timestamps
+    pub fn timescale(mut self, timescale: Option<(u32, TimescaleUnit)>) -> Ruler {
+        self.timescale = timescale;
+        self
+    }
+
+    // Times of the ticks, keeping about `spacing` columns between two ticks
+    pub fn ticks(&self, width: u16, spacing: u16) -> Vec<u64> {
+        let span = self.end.saturating_sub(self.start).max(1);
+        let count = (width / spacing.max(1)).max(1) as u64;
+        let step = tick_step(span.div_ceil(count));
+
+        let first = self.start.div_ceil(step) * step;
+        (0..)
+            .map(|i| first + i * step)
+            .take_while(|time| *time <= self.end)
+            .collect()
+    }
+}
+
+// Column of `time` in a window [start, end] drawn `width` columns wide
+pub fn time_column(time: u64, start: u64, end: u64, width: u16) -> u16 {
+    let span = end.saturating_sub(start).max(1) as u128;
+    let offset = time.clamp(start, end) - start;
+    (offset as u128 * width as u128 / span) as u16
+}
+
+// Format a timestamp in the unit that keeps `time` at or above 1, e.g. "1.5 us"
+pub fn format_time(time: u64, timescale: Option<(u32, TimescaleUnit)>) -> String {
+    format_time_in(time, timescale, time)
+}
+
+// Format a timestamp in the unit picked for `reference`, so that labels
+// formatted with the same reference share a unit
+pub fn format_time_in(
+    time: u64,
+    timescale: Option<(u32, TimescaleUnit)>,
+    reference: u64,
+) -> String {
+    let Some((magnitude, unit)) = timescale else {
+        return time.to_string();
+    };
+
+    let femtoseconds = |unit: TimescaleUnit| (TimescaleUnit::FS.divisor() / unit.divisor()) as u128;
+    let tick = magnitude as u128 * femtoseconds(unit);
+
+    let display = [
+        TimescaleUnit::S,
+        TimescaleUnit::MS,
+        TimescaleUnit::US,
+        TimescaleUnit::NS,
+        TimescaleUnit::PS,
+        TimescaleUnit::FS,
+    ]
+    .into_iter()
+    .find(|candidate| reference as u128 * tick >= femtoseconds(*candidate))
+    .unwrap_or(unit);
+
+    let value = (time as u128 * tick) as f64 / femtoseconds(display) as f64;
+    format!("{} {}", format_real(value), display)
+}
+
+// Parse a time given as raw timestamps ("1500") or with a unit ("1.5us", "20 ns"),
+// converted to the nearest timestamp of the dump
+pub fn parse_time(text: &str, timescale: Option<(u32, TimescaleUnit)>) -> Result<u64, String> {
+    let text = text.trim();
+    let invalid = || format!("invalid time '{}'", text);
+
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (number, unit) = (text[..split].trim(), &text[split..]);
+
+    if unit.is_empty() {
+        return number.parse::<u64>().map_err(|_| invalid());
+    }
+
+    let unit = unit.parse::<TimescaleUnit>().map_err(|_| invalid())?;
+    let number = number.parse::<f64>().map_err(|_| invalid())?;
+    if !number.is_finite() || number < 0.0 {
+        return Err(invalid());
+    }
+    let Some((magnitude, timescale_unit)) = timescale else {
+        return Err(format!(
+            "'{}' has a unit but the dump has no $timescale",
+            text
+        ));
+    };
+
+    // Timestamps per `unit`
+    let ticks = timescale_unit.divisor() as f64 / unit.divisor() as f64 / magnitude as f64;
+    Ok((number * ticks).round() as u64)
+}
+
+// Round a step up to 1, 2 or 5 times a power of ten
+fn tick_step(minimum: u64) -> u64 {
+    let mut power = 1u64;
+    loop {
+        for factor in [1, 2, 5] {
+            let step = power.saturating_mul(factor);
+            if step >= minimum || step == u64::MAX {
+                return step;
+            }
+        }
+        power = power.saturating_mul(10);
+    }
+}
+
+impl Widget for Ruler {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+
+        let marks = area.bottom() - 1;
+        for x in area.left()..area.right() {
+            buf.get_mut(x, marks).set_symbol("─").set_style(self.style);
+        }
+
+        // Labels that would overlap the previous one or the right edge are skipped
+        let ticks = self.ticks(area.width, 12);
+        let step = match ticks.as_slice() {
+            [first, second, ..] => second - first,
+            _ => self.end.saturating_sub(self.start),
+        };
+
+        let mut free = area.left();
+        for time in ticks {
+            let x = area.left()
+                + time_column(time, self.start, self.end, area.width).min(area.width - 1);
+            buf.get_mut(x, marks).set_symbol("┴").set_style(self.style);
+
+            let label = format_time_in(time, self.timescale, step);
+            if area.height > 1 && x >= free && x as usize + label.len() <= area.right() as usize {
+                buf.set_string(x, marks - 1, &label, self.label_style);
+                free = x + label.len() as u16 + 1;
+            }
+        }
+    }
+}
+
+// Vertical line at one time drawn over the ruler and the traces, with a label
+// on the first row. Blank cells get a line, drawn cells are highlighted
+pub struct TimeMarker {
+    time: u64,
+    start: u64,
+    end: u64,
+    label: String,
+    style: Style,
+}
+
+impl TimeMarker {
+    pub fn new(time: u64, start: u64, end: u64) -> TimeMarker {
+        TimeMarker {
+            time,
+            start,
+            end,
+            label: String::new(),
+            style: Style::default().fg(Color::Yellow),
+        }
+    }
+
+    pub fn label(mut self, label: String) -> TimeMarker {
+        self.label = label;
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> TimeMarker {
+        self.style = Style::default().fg(color);
+        self
+    }
+}
+
+impl Widget for TimeMarker {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width == 0 || area.height == 0 || self.time < self.start || self.time > self.end {
+            return;
+        }
+
+        let x = area.left()
+            + time_column(self.time, self.start, self.end, area.width).min(area.width - 1);
+        // Reversed video for markers drawn in the terminal's own colors
+        let highlight = match self.style.fg {
+            Some(Color::Reset) => Style::default().add_modifier(Modifier::REVERSED),
+            color => Style::default()
+                .fg(Color::Black)
+                .bg(color.unwrap_or(Color::Yellow)),
+        };
+
+        for y in area.top()..area.bottom() {
+            let cell = buf.get_mut(x, y);
+            if cell.symbol == " " {
+                cell.set_symbol("│").set_style(self.style);
+            } else {
+                cell.set_style(highlight);
+            }
+        }
+
+        // Keep the label inside the area, left of the line when it doesn't fit right of it
+        if !self.label.is_empty() {
+            let label = fit_label(&self.label, area.width as usize);
+            let width = label.chars().count() as u16;
+            let label_x = if x + width <= area.right() {
+                x
+            } else {
+                area.right().saturating_sub(width).max(area.left())
+            };
+            buf.set_string(label_x, area.top(), label, highlight);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_fall_on_round_times() {
+        assert_eq!(Ruler::new(0, 40).ticks(40, 10), vec![0, 10, 20, 30, 40]);
+        assert_eq!(Ruler::new(3, 97).ticks(80, 10), vec![20, 40, 60, 80]);
+        assert_eq!(
+            Ruler::new(0, 7).ticks(100, 10),
+            vec![0, 1, 2, 3, 4, 5, 6, 7]
+        );
+    }
+
+    #[test]
+    fn times_are_parsed_with_or_without_units() {
+        let timescale = Some((10, TimescaleUnit::PS));
+
+        assert_eq!(parse_time("1500", timescale), Ok(1500));
+        assert_eq!(parse_time("1.5ns", timescale), Ok(150));
+        assert_eq!(parse_time("2 us", timescale), Ok(200000));
+        assert!(parse_time("2 us", None).is_err());
+        assert!(parse_time("2 parsecs", timescale).is_err());
+    }
+
+    #[test]
+    fn times_are_formatted_in_timescale_units() {
+        let timescale = Some((10, TimescaleUnit::NS));
+
+        assert_eq!(format_time(0, timescale), "0 ns");
+        assert_eq!(format_time(5, timescale), "50 ns");
+        assert_eq!(format_time(150, timescale), "1.5 us");
+        assert_eq!(format_time_in(150, timescale, 10), "1500 ns");
+        assert_eq!(format_time(42, None), "42");
+    }
+}
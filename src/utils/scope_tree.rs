@@ -0,0 +1,182 @@
+use ratatui::{prelude::*, widgets::*};
+
+use super::waveform::Waveform;
+
+// A visible row of the scope tree pane
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TreeRow {
+    Scope(usize),
+    Signal(usize),
+}
+
+// Collapsible browser of the design hierarchy
+pub struct ScopeTree {
+    // Whether each scope of Waveform::scopes shows its children
+    pub expanded: Vec<bool>,
+    pub state: ListState,
+}
+
+impl ScopeTree {
+    pub fn new(waveform: &Waveform) -> ScopeTree {
+        let mut expanded = vec![false; waveform.scopes.len()];
+        for scope in waveform.top_scopes.iter() {
+            expanded[*scope] = true;
+        }
+
+        let mut state = ListState::default();
+        state.select(Some(0));
+
+        ScopeTree { expanded, state }
+    }
+
+    // Flatten the expanded part of the hierarchy into (row, depth) pairs
+    pub fn rows(&self, waveform: &Waveform) -> Vec<(TreeRow, usize)> {
+        let mut rows = Vec::new();
+
+        for scope in waveform.top_scopes.iter() {
+            self.push_scope(waveform, *scope, 0, &mut rows);
+        }
+        for signal in waveform.top_signals.iter() {
+            rows.push((TreeRow::Signal(*signal), 0));
+        }
+
+        rows
+    }
+
+    fn push_scope(
+        &self,
+        waveform: &Waveform,
+        scope: usize,
+        depth: usize,
+        rows: &mut Vec<(TreeRow, usize)>,
+    ) {
+        rows.push((TreeRow::Scope(scope), depth));
+
+        if !self.expanded[scope] {
+            return;
+        }
+
+        for child in waveform.scopes[scope].children.iter() {
+            self.push_scope(waveform, *child, depth + 1, rows);
+        }
+        for signal in waveform.scopes[scope].signals.iter() {
+            rows.push((TreeRow::Signal(*signal), depth + 1));
+        }
+    }
+
+    pub fn selected_row(&self, waveform: &Waveform) -> Option<TreeRow> {
+        let rows = self.rows(waveform);
+        self.state
+            .selected()
+            .and_then(|i| rows.get(i))
+            .map(|(row, _)| *row)
+    }
+
+    // Scope under the cursor, or the scope of the signal under the cursor
+    pub fn selected_scope(&self, waveform: &Waveform) -> Option<usize> {
+        match self.selected_row(waveform) {
+            Some(TreeRow::Scope(scope)) => Some(scope),
+            Some(TreeRow::Signal(signal)) => waveform.signals[signal].scope,
+            None => None,
+        }
+    }
+
+    pub fn next(&mut self, waveform: &Waveform) {
+        let length = self.rows(waveform).len();
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < length => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self, waveform: &Waveform) {
+        let length = self.rows(waveform).len();
+        let i = match self.state.selected() {
+            Some(i) if i > 0 => i - 1,
+            _ => length.saturating_sub(1),
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn toggle(&mut self, scope: usize) {
+        self.expanded[scope] = !self.expanded[scope];
+    }
+
+    // Draw the tree, marking the signals currently shown on the Plot tab
+    pub fn render<B: Backend>(
+        &mut self,
+        f: &mut Frame<B>,
+        area: Rect,
+        waveform: &Waveform,
+        plotted: &[usize],
+        focused: bool,
+    ) {
+        let items: Vec<ListItem> = self
+            .rows(waveform)
+            .iter()
+            .map(|(row, depth)| {
+                let indent = "  ".repeat(*depth);
+                match row {
+                    TreeRow::Scope(scope) => {
+                        let node = &waveform.scopes[*scope];
+                        let arrow = if self.expanded[*scope] { "▾" } else { "▸" };
+                        ListItem::new(Line::from(vec![
+                            Span::raw(format!("{}{} ", indent, arrow)),
+                            Span::styled(
+                                format!("{} ", node.scope_type),
+                                Style::default().fg(Color::Gray),
+                            ),
+                            Span::styled(
+                                node.name.clone(),
+                                Style::default().fg(Color::LightGreen).bold(),
+                            ),
+                        ]))
+                    }
+                    TreeRow::Signal(signal) => {
+                        let variable = &waveform.signals[*signal];
+                        let mark = if plotted.contains(signal) {
+                            "●"
+                        } else {
+                            "○"
+                        };
+                        let index = match variable.index {
+                            Some(index) => index.to_string(),
+                            None => String::new(),
+                        };
+                        ListItem::new(Line::from(vec![
+                            Span::styled(
+                                format!("{}{} ", indent, mark),
+                                Style::default().fg(Color::LightCyan),
+                            ),
+                            Span::styled(
+                                format!("{}{}", variable.reference, index),
+                                Style::default().fg(Color::Gray),
+                            ),
+                        ]))
+                    }
+                }
+            })
+            .collect();
+
+        let border_style = if focused {
+            Style::default().fg(Color::LightCyan)
+        } else {
+            Style::default().fg(Color::Blue)
+        };
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border_style)
+                    .title(Span::styled(
+                        "Scopes (Enter to expand or add, Space to add all)",
+                        Style::default().add_modifier(Modifier::BOLD),
+                    )),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
@@ -0,0 +1,389 @@
+use vcd::{ReferenceIndex, Value};
+
+use super::{
+    radix::Radix,
+    trace::format_value,
+    waveform::{Signal, SignalValue, Waveform},
+};
+
+// How the value of a signal is compared in a search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    // The printed value matches a pattern with * and ? wildcards
+    Matches,
+}
+
+// Right hand side of a search
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    Bits(Vec<Value>),
+    Real(f64),
+    Text(String),
+}
+
+// A search such as `data == 0x81`, `addr[7:4] == 4'hA` or `state ~ "IDLE"`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub signal: String,
+    pub slice: Option<ReferenceIndex>,
+    pub comparison: Comparison,
+    pub operand: Operand,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, String> {
+        // The first operator outside of quotes, as quoted text may contain one
+        let mut quoted = false;
+        let (left, comparison, right) = text
+            .char_indices()
+            .find_map(|(at, c)| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                [
+                    ("==", Comparison::Equal),
+                    ("!=", Comparison::NotEqual),
+                    ("~", Comparison::Matches),
+                ]
+                .into_iter()
+                .find(|(operator, _)| !quoted && text[at..].starts_with(operator))
+                .map(|(operator, comparison)| {
+                    let right = &text[at + operator.len()..];
+                    (text[..at].trim(), comparison, right.trim())
+                })
+            })
+            .ok_or("expected '==', '!=' or '~'")?;
+
+        let (signal, slice) = match left.find('[') {
+            Some(bracket) => {
+                let slice = left[bracket..]
+                    .parse::<ReferenceIndex>()
+                    .map_err(|_| format!("invalid bit select '{}'", &left[bracket..]))?;
+                (left[..bracket].trim(), Some(slice))
+            }
+            None => (left, None),
+        };
+        if signal.is_empty() {
+            return Err(String::from("missing signal name"));
+        }
+
+        let operand = if let Some(text) = right.strip_prefix('"') {
+            Operand::Text(text.strip_suffix('"').unwrap_or(text).to_string())
+        } else if comparison == Comparison::Matches {
+            Operand::Text(right.to_string())
+        } else if let Ok(bits) = parse_literal(right) {
+            Operand::Bits(bits)
+        } else if let Ok(real) = right.parse::<f64>() {
+            Operand::Real(real)
+        } else {
+            return Err(format!("invalid value '{}'", right));
+        };
+
+        Ok(Query {
+            signal: signal.to_string(),
+            slice,
+            comparison,
+            operand,
+        })
+    }
+
+    // Whether a value of `signal` satisfies the query, vectors printed in `radix` for patterns
+    pub fn matches(&self, signal: &Signal, value: &SignalValue, radix: Radix) -> bool {
+        let value = match (&self.slice, value) {
+            (Some(slice), value) => match select_bits(signal, value, *slice) {
+                Some(bits) => SignalValue::Vector(bits.into_iter().collect()),
+                None => return false,
+            },
+            (None, value) => value.clone(),
+        };
+
+        let equal = match (&self.operand, &value) {
+            (Operand::Text(pattern), _) if self.comparison == Comparison::Matches => {
+                return glob_match(pattern, &format_value(&value, radix));
+            }
+            (Operand::Bits(bits), SignalValue::Scalar(_) | SignalValue::Vector(_)) => {
+                same_bits(&value_bits(&value), bits)
+            }
+            (Operand::Bits(bits), SignalValue::Real(real)) => {
+                to_integer(bits).is_some_and(|integer| integer as f64 == *real)
+            }
+            (Operand::Real(expected), SignalValue::Real(real)) => expected == real,
+            (Operand::Text(text), SignalValue::String(string)) => text == string,
+            (Operand::Text(text), _) => *text == format_value(&value, radix),
+            _ => false,
+        };
+
+        match self.comparison {
+            Comparison::NotEqual => !equal,
+            _ => equal,
+        }
+    }
+}
+
+// Parse a number as Verilog (`4'hA`, `8'b1010_0000`, `'d10`), with a C style prefix
+// (`0x81`, `0b101`, `0o17`) or in decimal, into bits, most significant first.
+// Hexadecimal, octal and binary digits may be x or z
+pub fn parse_literal(text: &str) -> Result<Vec<Value>, String> {
+    let text = text.replace('_', "");
+    let invalid = || format!("invalid number '{}'", text);
+
+    let (width, base, digits) = if let Some((width, rest)) = text.split_once('\'') {
+        let width = match width {
+            "" => None,
+            width => Some(width.parse::<usize>().map_err(|_| invalid())?),
+        };
+        let mut chars = rest.chars();
+        let base = match chars.next().map(|c| c.to_ascii_lowercase()) {
+            Some('h') => 16,
+            Some('d') => 10,
+            Some('o') => 8,
+            Some('b') => 2,
+            _ => return Err(invalid()),
+        };
+        (width, base, chars.as_str().to_string())
+    } else if let Some(digits) = text.strip_prefix("0x") {
+        (None, 16, digits.to_string())
+    } else if let Some(digits) = text.strip_prefix("0b") {
+        (None, 2, digits.to_string())
+    } else if let Some(digits) = text.strip_prefix("0o") {
+        (None, 8, digits.to_string())
+    } else {
+        (None, 10, text.clone())
+    };
+
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut bits = if base == 10 {
+        let number = digits.parse::<u128>().map_err(|_| invalid())?;
+        let width = (128 - number.leading_zeros()).max(1);
+        (0..width)
+            .rev()
+            .map(|bit| bool_value(number >> bit & 1 == 1))
+            .collect()
+    } else {
+        let digit_width = match base {
+            16 => 4,
+            8 => 3,
+            _ => 1,
+        };
+        let mut bits = Vec::new();
+        for digit in digits.chars() {
+            match digit.to_ascii_lowercase() {
+                'x' => bits.extend(vec![Value::X; digit_width]),
+                'z' | '?' => bits.extend(vec![Value::Z; digit_width]),
+                digit => {
+                    let value = digit.to_digit(base).ok_or_else(invalid)?;
+                    bits.extend(
+                        (0..digit_width)
+                            .rev()
+                            .map(|bit| bool_value(value >> bit & 1 == 1)),
+                    );
+                }
+            }
+        }
+        bits
+    };
+
+    if let Some(width) = width {
+        bits = resize(&bits, width);
+    }
+
+    Ok(bits)
+}
+
+fn bool_value(bit: bool) -> Value {
+    if bit {
+        Value::V1
+    } else {
+        Value::V0
+    }
+}
+
+// Bits of a scalar or vector value, most significant first
+pub fn value_bits(value: &SignalValue) -> Vec<Value> {
+    match value {
+        SignalValue::Scalar(value) => vec![*value],
+        SignalValue::Vector(vector) => vector.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Keep the `width` least significant bits, zero extending narrower values
+pub fn resize(bits: &[Value], width: usize) -> Vec<Value> {
+    if bits.len() >= width {
+        bits[bits.len() - width..].to_vec()
+    } else {
+        let mut resized = vec![Value::V0; width - bits.len()];
+        resized.extend_from_slice(bits);
+        resized
+    }
+}
+
+// Unsigned value of known bits, None with x or z bits or more than 128 bits
+pub fn to_integer(bits: &[Value]) -> Option<u128> {
+    if bits.len() > 128 {
+        return None;
+    }
+    bits.iter().try_fold(0u128, |value, bit| match bit {
+        Value::V0 => Some(value << 1),
+        Value::V1 => Some(value << 1 | 1),
+        _ => None,
+    })
+}
+
+// Compare two vectors as numbers of the wider width
+fn same_bits(left: &[Value], right: &[Value]) -> bool {
+    let width = left.len().max(right.len());
+    resize(left, width) == resize(right, width)
+}
+
+// Bits of a value selected as in `addr[7:4]`, using the declared index of the signal
+pub fn select_bits(
+    signal: &Signal,
+    value: &SignalValue,
+    slice: ReferenceIndex,
+) -> Option<Vec<Value>> {
+    let bits = value_bits(value);
+    let (msb, lsb) = match signal.index {
+        Some(ReferenceIndex::Range(msb, lsb)) => (msb, lsb),
+        Some(ReferenceIndex::BitSelect(index)) => (index, index),
+        None => (bits.len() as i32 - 1, 0),
+    };
+
+    // Position of a declared index in the most significant first bits
+    let position = |index: i32| {
+        let position = if msb >= lsb { msb - index } else { index - msb };
+        usize::try_from(position)
+            .ok()
+            .filter(|position| *position < bits.len())
+    };
+
+    let (first, last) = match slice {
+        ReferenceIndex::Range(left, right) => (position(left)?, position(right)?),
+        ReferenceIndex::BitSelect(index) => (position(index)?, position(index)?),
+    };
+
+    Some(bits[first.min(last)..=first.max(last)].to_vec())
+}
+
+// Match `text` against a pattern where * stands for any run of characters and
+// ? for one character. Patterns without wildcards match anywhere in the text
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    if !pattern.contains(['*', '?']) {
+        return text.contains(pattern);
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // matched[j]: whether the pattern read so far matches text[..j]
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    for symbol in pattern {
+        let previous = matched.clone();
+        matched[0] = symbol == '*' && previous[0];
+        for j in 1..=text.len() {
+            matched[j] = match symbol {
+                '*' => previous[j] || matched[j - 1],
+                '?' => previous[j - 1],
+                symbol => previous[j - 1] && text[j - 1] == symbol,
+            };
+        }
+    }
+
+    matched[text.len()]
+}
+
+// First time strictly after `time` at which `signal` changes to a value matching the query
+pub fn find_next(
+    waveform: &Waveform,
+    signal: &Signal,
+    query: &Query,
+    time: u64,
+    radix: Radix,
+) -> Option<u64> {
+    let changes = waveform.signal_changes(signal);
+    let first = changes.partition_point(|(change_time, _)| *change_time <= time);
+
+    changes[first..]
+        .iter()
+        .find(|(_, value)| query.matches(signal, value, radix))
+        .map(|(change_time, _)| *change_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::waveform::tests::load_fixture;
+
+    fn bits(text: &str) -> Vec<Value> {
+        text.chars()
+            .map(|c| c.to_string().parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn literals_are_parsed_into_bits() {
+        assert_eq!(parse_literal("0x81"), Ok(bits("10000001")));
+        assert_eq!(parse_literal("4'hA"), Ok(bits("1010")));
+        assert_eq!(parse_literal("6'b1_01"), Ok(bits("000101")));
+        assert_eq!(parse_literal("'d5"), Ok(bits("101")));
+        assert_eq!(parse_literal("8'hx1"), Ok(bits("xxxx0001")));
+        assert_eq!(parse_literal("10"), Ok(bits("1010")));
+        assert!(parse_literal("0xg").is_err());
+    }
+
+    #[test]
+    fn queries_find_the_next_matching_change() {
+        let waveform = load_fixture("test6.vcd");
+        let signal = |reference: &str| {
+            waveform
+                .signals
+                .iter()
+                .find(|signal| signal.reference == reference)
+                .unwrap()
+        };
+        let find = |query: &str, reference: &str, time: u64| {
+            let query = Query::parse(query).unwrap();
+            find_next(
+                &waveform,
+                signal(reference),
+                &query,
+                time,
+                Radix::Hexadecimal,
+            )
+        };
+
+        assert_eq!(find("a == 0xff", "a", 0), Some(25));
+        assert_eq!(find("a[1:0] == 2'b11", "a", 0), Some(15));
+        assert_eq!(find("a[2] != 0", "a", 15), Some(25));
+        assert_eq!(find("y ~ 0?", "y", 0), Some(15));
+        assert_eq!(find("y == 8", "y", 15), None);
+        assert!(Query::parse("a = 1").is_err());
+        assert_eq!(
+            Query::parse(r#"state ~ "a==b""#),
+            Ok(Query {
+                signal: String::from("state"),
+                slice: None,
+                comparison: Comparison::Matches,
+                operand: Operand::Text(String::from("a==b")),
+            })
+        );
+        assert_eq!(
+            Query::parse(r#"state != "~""#).map(|query| query.operand),
+            Ok(Operand::Text(String::from("~")))
+        );
+    }
+
+    #[test]
+    fn patterns_use_wildcards() {
+        assert!(glob_match("IDLE", "STATE_IDLE"));
+        assert!(glob_match("WR*_0x??", "WRITE_0x41"));
+        assert!(!glob_match("WR*_0x?", "WRITE_0x41"));
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+
+use super::loader::format_size;
+
+// Lines between two entries of the line index of a file
+pub const LINE_STEP: usize = 1024;
+
+// Bytes kept of a document that can't be read again, such as stdin
+pub const MAX_TEXT_BYTES: u64 = 16 << 20;
+
+// Text of the document a waveform was read from, shown on the VCD Code tab
+pub enum Source {
+    // Held in memory, for documents that are small or can't be read again
+    Text(String),
+    // Read again from the file when shown, with the offset of every LINE_STEP-th line
+    // so that a few lines can be shown from anywhere in a large file
+    File {
+        path: PathBuf,
+        line_starts: Vec<u64>,
+        lines: usize,
+    },
+}
+
+impl Default for Source {
+    fn default() -> Source {
+        Source::Text(String::new())
+    }
+}
+
+impl Source {
+    pub fn line_count(&self) -> usize {
+        match self {
+            Source::Text(text) => text.lines().count(),
+            Source::File { lines, .. } => *lines,
+        }
+    }
+
+    // Up to `count` lines starting with line `first`, counted from 0
+    pub fn lines(&self, first: usize, count: usize) -> io::Result<Vec<String>> {
+        match self {
+            Source::Text(text) => Ok(text
+                .lines()
+                .skip(first)
+                .take(count)
+                .map(String::from)
+                .collect()),
+            Source::File {
+                path,
+                line_starts,
+                lines,
+            } => {
+                let first = first.min(*lines);
+                let step = (first / LINE_STEP).min(line_starts.len().saturating_sub(1));
+
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(line_starts.get(step).copied().unwrap_or(0)))?;
+
+                // Lines past the ones indexed so far may still be being written
+                BufReader::new(file)
+                    .lines()
+                    .skip(first - step * LINE_STEP)
+                    .take(count.min(lines - first))
+                    .collect()
+            }
+        }
+    }
+}
+
+// Reader counting the bytes and lines read through it, noting where every
+// LINE_STEP-th line starts
+pub struct TrackedReader<R> {
+    inner: R,
+    pub bytes: u64,
+    // Line breaks read, and whether a line was started after the last one
+    lines: usize,
+    partial_line: bool,
+    pub line_starts: Vec<u64>,
+    // Text read since it was last taken, up to MAX_TEXT_BYTES in all, when kept
+    pub text: Option<Vec<u8>>,
+}
+
+impl<R> TrackedReader<R> {
+    pub fn new(inner: R) -> TrackedReader<R> {
+        TrackedReader {
+            inner,
+            bytes: 0,
+            lines: 0,
+            partial_line: false,
+            line_starts: vec![0],
+            text: None,
+        }
+    }
+
+    // Keep the text read, for documents that can't be read again
+    pub fn keep_text(mut self) -> TrackedReader<R> {
+        self.text = Some(Vec::new());
+        self
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines + self.partial_line as usize
+    }
+
+    fn track(&mut self, data: &[u8]) {
+        for (offset, byte) in data.iter().enumerate() {
+            if *byte == b'\n' {
+                self.lines += 1;
+                if self.lines.is_multiple_of(LINE_STEP) {
+                    self.line_starts.push(self.bytes + offset as u64 + 1);
+                }
+            }
+        }
+        if let Some(last) = data.last() {
+            self.partial_line = *last != b'\n';
+        }
+        if let Some(text) = &mut self.text {
+            let room = MAX_TEXT_BYTES.saturating_sub(self.bytes) as usize;
+            text.extend_from_slice(&data[..data.len().min(room)]);
+            if self.bytes <= MAX_TEXT_BYTES && data.len() > room {
+                let note = format!(
+                    "\n(Only the first {} are shown)\n",
+                    format_size(MAX_TEXT_BYTES)
+                );
+                text.extend_from_slice(note.as_bytes());
+            }
+        }
+        self.bytes += data.len() as u64;
+    }
+}
+
+impl<R: Read> Read for TrackedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.track(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for TrackedReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        let consumed = match self.inner.fill_buf() {
+            Ok(buf) => buf[..amount].to_vec(),
+            Err(_) => Vec::new(),
+        };
+        self.track(&consumed);
+        self.inner.consume(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_read_from_anywhere_in_a_file() {
+        let text: String = (0..3 * LINE_STEP)
+            .map(|i| format!("#{}\n1!\n", i * 5))
+            .chain([String::from("$comment unterminated")])
+            .collect();
+        let path = std::env::temp_dir().join(format!("waveplot-source-{}.vcd", std::process::id()));
+        std::fs::write(&path, &text).unwrap();
+
+        let mut reader =
+            TrackedReader::new(BufReader::with_capacity(7, File::open(&path).unwrap()));
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert_eq!(reader.bytes, text.len() as u64);
+
+        let file = Source::File {
+            path: PathBuf::from(&path),
+            lines: reader.line_count(),
+            line_starts: reader.line_starts,
+        };
+        let text = Source::Text(text);
+        assert_eq!(file.line_count(), text.line_count());
+
+        let last = text.line_count() - 2;
+        for first in [0, LINE_STEP - 1, LINE_STEP + 3, 5 * LINE_STEP + 1, last] {
+            assert_eq!(file.lines(first, 5).unwrap(), text.lines(first, 5).unwrap());
+        }
+        assert_eq!(file.lines(last, 5).unwrap().len(), 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+use super::waveform::SignalValue;
+
+// Entries of a level of a RangeSummary covered by one entry of the next level
+const FANOUT: usize = 32;
+
+// Times sampled in a busy column to find the value it holds the longest
+const DOMINANT_SAMPLES: u64 = 16;
+
+// What a signal does during the slice of time covered by one terminal column
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Column<'a> {
+    // Value when the column starts and after its last change, None before the first change
+    pub first: Option<&'a SignalValue>,
+    pub last: Option<&'a SignalValue>,
+    // Changes within the column
+    pub transitions: usize,
+    // Value held for most of the column
+    pub dominant: Option<&'a SignalValue>,
+    // Indexes of the changes holding a value during the column, for RangeSummary::range
+    pub changes: (usize, usize),
+}
+
+impl Column<'_> {
+    // Whether the signal changes more than once within the column, too often to be drawn
+    pub fn is_busy(&self) -> bool {
+        self.transitions > 1
+    }
+}
+
+// Summary of every column of an area `width` columns wide showing the changes of a
+// signal over [start, end]. Takes a binary search per column whatever the number
+// of changes, so zoomed out views of long dumps are as quick to draw as close ups
+pub fn summarize(
+    changes: &[(u64, SignalValue)],
+    start: u64,
+    end: u64,
+    width: u16,
+) -> Vec<Column<'_>> {
+    let span = end.saturating_sub(start).max(1) as u128;
+    // First time of a column, so that column c shows the times t with
+    // (t - start) * width / span == c
+    let column_start = |column: u16| start + (column as u128 * span).div_ceil(width as u128) as u64;
+    // Changes before a column, those at the start of the window being part of its value
+    let changes_before = |column: u16| {
+        let time = if column == 0 {
+            start.saturating_add(1)
+        } else {
+            column_start(column)
+        };
+        changes.partition_point(|(change_time, _)| *change_time < time)
+    };
+    let value_before = |index: usize| index.checked_sub(1).map(|index| &changes[index].1);
+
+    let mut columns = Vec::with_capacity(width as usize);
+    let mut before = changes_before(0);
+    for column in 0..width {
+        let after = changes_before(column + 1);
+        let (from, to) = (column_start(column).max(start), column_start(column + 1));
+
+        let dominant = if after - before > 1 {
+            dominant_value(&changes[..after], from, to, before)
+        } else if after > before && changes[before].0 - from < to - changes[before].0 {
+            value_before(after)
+        } else {
+            value_before(before)
+        };
+
+        columns.push(Column {
+            first: value_before(before),
+            last: value_before(after),
+            transitions: after - before,
+            dominant,
+            changes: (before.saturating_sub(1), after),
+        });
+        before = after;
+    }
+
+    columns
+}
+
+// Value held the longest between `from` and `to` by `changes`, which end in this
+// span and start changing in it at index `first`. Found exactly for a few changes,
+// and from evenly spaced samples for many
+fn dominant_value(
+    changes: &[(u64, SignalValue)],
+    from: u64,
+    to: u64,
+    first: usize,
+) -> Option<&SignalValue> {
+    // Time every value is held for, or the samples it was found at
+    let mut held: Vec<(&SignalValue, u64)> = Vec::new();
+    fn hold<'a>(held: &mut Vec<(&'a SignalValue, u64)>, value: &'a SignalValue, time: u64) {
+        match held.iter_mut().find(|(other, _)| *other == value) {
+            Some((_, total)) => *total += time,
+            None => held.push((value, time)),
+        }
+    }
+
+    if changes.len() - first <= DOMINANT_SAMPLES as usize {
+        let mut time = from;
+        for index in first..changes.len() {
+            if index > 0 {
+                hold(&mut held, &changes[index - 1].1, changes[index].0 - time);
+            }
+            time = changes[index].0;
+        }
+        hold(&mut held, &changes[changes.len() - 1].1, to - time);
+    } else {
+        let step = (to - from).max(1) as f64 / DOMINANT_SAMPLES as f64;
+        for sample in 0..DOMINANT_SAMPLES {
+            let time = from + (step * (sample as f64 + 0.5)) as u64;
+            let index = changes.partition_point(|(change_time, _)| *change_time <= time);
+            if index > 0 {
+                hold(&mut held, &changes[index - 1].1, 1);
+            }
+        }
+    }
+
+    // The first value found wins a tie, as it was taken earlier
+    held.into_iter()
+        .fold(
+            None,
+            |best: Option<(&SignalValue, u64)>, (value, time)| match best {
+                Some((_, best_time)) if best_time >= time => best,
+                _ => Some((value, time)),
+            },
+        )
+        .map(|(value, _)| value)
+}
+
+// Lowest and highest values of a real signal over runs of FANOUT changes, then of
+// FANOUT such runs and so on, so that the range of any run of changes is found
+// from a few entries of every level
+#[derive(Default)]
+pub struct RangeSummary {
+    levels: Vec<Vec<(f64, f64)>>,
+    // Changes summarized so far
+    len: usize,
+}
+
+impl RangeSummary {
+    // Summarize the changes added since the last update. The last change summarized
+    // may have been replaced, so the runs it is part of are summarized again
+    pub fn update(&mut self, changes: &[(u64, SignalValue)]) {
+        let mut from = self.len.saturating_sub(1) / FANOUT;
+        let mut len = changes.len().div_ceil(FANOUT);
+        let mut level = 0;
+
+        while len > 1 || level == 0 {
+            if self.levels.len() == level {
+                self.levels.push(Vec::new());
+            }
+
+            let mut entries = std::mem::take(&mut self.levels[level]);
+            entries.truncate(from);
+            for entry in from..len {
+                let first = entry * FANOUT;
+                let range = if level == 0 {
+                    let last = (first + FANOUT).min(changes.len());
+                    changes[first..last]
+                        .iter()
+                        .filter_map(|(_, value)| real(value))
+                        .map(|value| (value, value))
+                        .reduce(merge)
+                } else {
+                    let below = &self.levels[level - 1];
+                    below[first..(first + FANOUT).min(below.len())]
+                        .iter()
+                        .copied()
+                        .reduce(merge)
+                };
+                entries.push(range.unwrap_or((f64::INFINITY, f64::NEG_INFINITY)));
+            }
+            self.levels[level] = entries;
+
+            from /= FANOUT;
+            len = len.div_ceil(FANOUT);
+            level += 1;
+        }
+
+        self.levels.truncate(level);
+        self.len = changes.len();
+    }
+
+    // Lowest and highest values of changes[first..last], None without any real value
+    pub fn range(
+        &self,
+        changes: &[(u64, SignalValue)],
+        first: usize,
+        last: usize,
+    ) -> Option<(f64, f64)> {
+        let mut range: Option<(f64, f64)> = None;
+        let mut include = |entry: (f64, f64)| {
+            if entry.0 <= entry.1 {
+                range = Some(range.map_or(entry, |range| merge(range, entry)));
+            }
+        };
+
+        // Single changes up to the runs of the first level, then whole runs of
+        // every level up to those of the next one
+        let (mut first, mut last) = (first, last.min(self.len));
+        for level in 0..=self.levels.len() {
+            let aligned = level < self.levels.len();
+            let entry = |index: usize| match level {
+                0 => real(&changes[index].1).map_or((f64::INFINITY, f64::NEG_INFINITY), |v| (v, v)),
+                _ => self.levels[level - 1][index],
+            };
+
+            while first < last && (!aligned || first % FANOUT != 0) {
+                include(entry(first));
+                first += 1;
+            }
+            while last > first && last % FANOUT != 0 {
+                last -= 1;
+                include(entry(last));
+            }
+            first /= FANOUT;
+            last /= FANOUT;
+        }
+
+        range
+    }
+}
+
+fn real(value: &SignalValue) -> Option<f64> {
+    match value {
+        SignalValue::Real(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn merge(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0.min(b.0), a.1.max(b.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use vcd::Value;
+
+    #[test]
+    fn columns_count_the_changes_they_cover() {
+        // Held until 50, then toggling at every timestamp
+        let changes: Vec<(u64, SignalValue)> = [0]
+            .into_iter()
+            .chain(50..100)
+            .map(|time| {
+                let value = if time % 2 == 0 { Value::V0 } else { Value::V1 };
+                (time, SignalValue::Scalar(value))
+            })
+            .collect();
+
+        let columns = summarize(&changes, 0, 100, 10);
+        assert_eq!(columns.len(), 10);
+        assert_eq!(columns[0].transitions, 0);
+        assert_eq!(columns[4].dominant, Some(&SignalValue::Scalar(Value::V0)));
+        assert_eq!(columns[6].transitions, 10);
+        assert!(columns[6].is_busy());
+        assert_eq!(columns[6].last, Some(&SignalValue::Scalar(Value::V1)));
+    }
+
+    #[test]
+    fn ranges_are_found_across_levels() {
+        let changes: Vec<(u64, SignalValue)> = (0..5000u64)
+            .map(|time| (time, SignalValue::Real(((time * 7919) % 1000) as f64)))
+            .collect();
+
+        let mut summary = RangeSummary::default();
+        summary.update(&changes[..1234]);
+        summary.update(&changes);
+
+        for (first, last) in [(0, 5000), (3, 4), (31, 33), (100, 3000), (1023, 1025)] {
+            let expected = changes[first..last]
+                .iter()
+                .filter_map(|(_, value)| real(value))
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |range, v| {
+                    merge(range, (v, v))
+                });
+            assert_eq!(summary.range(&changes, first, last), Some(expected));
+        }
+        assert_eq!(summary.range(&changes, 10, 10), None);
+    }
+}
@@ -0,0 +1,516 @@
+use vcd::{Value, VarType};
+
+use std::fmt::Write;
+
+use super::{
+    radix::Radix,
+    ruler::{format_time, format_time_in, Ruler},
+    trace::{fit_label, format_real, format_value, AnalogTrace, Interpolation},
+    waveform::{SignalValue, Waveform},
+};
+
+// Sizes in pixels: rows of the ruler and of every signal, the height of a trace
+// inside its row, and the width of a character of the 12px monospace font
+const RULER_HEIGHT: f64 = 44.0;
+const ROW_HEIGHT: f64 = 32.0;
+const TRACE_HEIGHT: f64 = 20.0;
+const CHAR_WIDTH: f64 = 7.2;
+
+// Print friendly colors on a white background
+const BACKGROUND: &str = "#ffffff";
+const TEXT: &str = "#1f2328";
+const GRID: &str = "#d0d7de";
+const TRACE: &str = "#0969da";
+const UNKNOWN: &str = "#cf222e";
+const HIGH_IMPEDANCE: &str = "#bf8700";
+const MARKER: &str = "#8250df";
+const CURSOR: &str = "#e16f24";
+
+// Signals of a waveform over a time window as an SVG document, laid out like the
+// Plot tab: names on the left, a time ruler on top and one row per signal
+pub struct SvgPlot<'a> {
+    waveform: &'a Waveform,
+    // Indexes into Waveform::signals, one row each
+    signals: &'a [usize],
+    start: u64,
+    end: u64,
+    // Per signal settings indexed like Waveform::signals, `radix` for the missing ones
+    radix: Radix,
+    radixes: &'a [Radix],
+    interpolations: &'a [Interpolation],
+    cursor: Option<u64>,
+    markers: &'a [(char, u64)],
+    width: u32,
+}
+
+impl<'a> SvgPlot<'a> {
+    pub fn new(waveform: &'a Waveform, signals: &'a [usize], start: u64, end: u64) -> SvgPlot<'a> {
+        SvgPlot {
+            waveform,
+            signals,
+            start,
+            end,
+            radix: Radix::Hexadecimal,
+            radixes: &[],
+            interpolations: &[],
+            cursor: None,
+            markers: &[],
+            width: 1200,
+        }
+    }
+
+    pub fn radix(mut self, radix: Radix) -> SvgPlot<'a> {
+        self.radix = radix;
+        self
+    }
+
+    pub fn radixes(mut self, radixes: &'a [Radix]) -> SvgPlot<'a> {
+        self.radixes = radixes;
+        self
+    }
+
+    pub fn interpolations(mut self, interpolations: &'a [Interpolation]) -> SvgPlot<'a> {
+        self.interpolations = interpolations;
+        self
+    }
+
+    pub fn cursor(mut self, cursor: Option<u64>) -> SvgPlot<'a> {
+        self.cursor = cursor;
+        self
+    }
+
+    pub fn markers(mut self, markers: &'a [(char, u64)]) -> SvgPlot<'a> {
+        self.markers = markers;
+        self
+    }
+
+    // Width of the document in pixels, names included
+    pub fn width(mut self, width: u32) -> SvgPlot<'a> {
+        self.width = width;
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let waveform = self.waveform;
+
+        let longest_name = self
+            .signals
+            .iter()
+            .map(|signal| {
+                waveform
+                    .signal_path(&waveform.signals[*signal])
+                    .chars()
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        let left = (longest_name as f64 * CHAR_WIDTH + 16.0).max(80.0);
+        let width = (self.width as f64).max(left + 100.0);
+        let height = RULER_HEIGHT + self.signals.len() as f64 * ROW_HEIGHT + 8.0;
+        let axis = Axis {
+            start: self.start,
+            end: self.end.max(self.start + 1),
+            left,
+            right: width - 8.0,
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" font-size="12">"#,
+            w = width,
+            h = height
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            BACKGROUND
+        );
+
+        self.render_ruler(&mut svg, &axis, height);
+
+        for (row, index) in self.signals.iter().enumerate() {
+            let signal = &waveform.signals[*index];
+            let top = RULER_HEIGHT + row as f64 * ROW_HEIGHT + (ROW_HEIGHT - TRACE_HEIGHT) / 2.0;
+            let _ = writeln!(
+                svg,
+                r#"<text x="8" y="{}" fill="{}">{}</text>"#,
+                top + TRACE_HEIGHT / 2.0 + 4.0,
+                TEXT,
+                escape(&waveform.signal_path(signal))
+            );
+
+            let changes = waveform.signal_changes(signal);
+            if signal.var_type == VarType::Real {
+                let interpolation = self
+                    .interpolations
+                    .get(*index)
+                    .copied()
+                    .unwrap_or(Interpolation::Step);
+                render_real(&mut svg, &axis, top, changes, interpolation);
+            } else if signal.size == 1
+                && signal.var_type != VarType::String
+                && changes
+                    .iter()
+                    .all(|(_, value)| matches!(value, SignalValue::Scalar(_)))
+            {
+                render_scalar(&mut svg, &axis, top, changes);
+            } else {
+                let radix = self.radixes.get(*index).copied().unwrap_or(self.radix);
+                render_bus(&mut svg, &axis, top, changes, radix);
+            }
+        }
+
+        for (name, time) in self.markers.iter() {
+            render_time_line(&mut svg, &axis, *time, &name.to_string(), MARKER, height);
+        }
+        if let Some(cursor) = self.cursor {
+            let label = format_time(cursor, waveform.timescale);
+            render_time_line(&mut svg, &axis, cursor, &label, CURSOR, height);
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    // Tick labels over a line with tick marks, and faint grid lines through the rows
+    fn render_ruler(&self, svg: &mut String, axis: &Axis, height: f64) {
+        let ruler = Ruler::new(axis.start, axis.end);
+        let ticks = ruler.ticks((axis.right - axis.left) as u16, 100);
+        let step = match ticks.as_slice() {
+            [first, second, ..] => second - first,
+            _ => axis.end - axis.start,
+        };
+
+        let baseline = RULER_HEIGHT - 6.0;
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{}" y1="{b}" x2="{}" y2="{b}" stroke="{}"/>"#,
+            axis.left,
+            axis.right,
+            TEXT,
+            b = baseline
+        );
+        for time in ticks {
+            let x = axis.x(time);
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{x}" y1="{}" x2="{x}" y2="{}" stroke="{}"/>"#,
+                baseline - 4.0,
+                height,
+                GRID
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" fill="{}">{}</text>"#,
+                x + 2.0,
+                baseline - 6.0,
+                TEXT,
+                escape(&format_time_in(time, self.waveform.timescale, step))
+            );
+        }
+    }
+}
+
+// Horizontal mapping of the time window to pixels
+struct Axis {
+    start: u64,
+    end: u64,
+    left: f64,
+    right: f64,
+}
+
+impl Axis {
+    // Rounded to a tenth of a pixel to keep the document short
+    fn x(&self, time: u64) -> f64 {
+        let time = time.clamp(self.start, self.end);
+        let x = self.left
+            + (time - self.start) as f64 / (self.end - self.start) as f64
+                * (self.right - self.left);
+        (x * 10.0).round() / 10.0
+    }
+
+    // Visible (first x, last x, value, starts with a change, ends with a change) runs
+    fn segments<'a>(
+        &self,
+        changes: &'a [(u64, SignalValue)],
+    ) -> Vec<(f64, f64, &'a SignalValue, bool, bool)> {
+        let mut segments = Vec::new();
+
+        for (i, (time, value)) in changes.iter().enumerate() {
+            let next = changes.get(i + 1).map(|(next, _)| *next);
+            if next.is_some_and(|next| next <= self.start) || *time > self.end {
+                continue;
+            }
+
+            let first = self.x(*time);
+            let last = next.map_or(self.right, |next| self.x(next));
+            if first < last {
+                let ends = next.is_some_and(|next| next < self.end);
+                segments.push((first, last, value, *time > self.start, ends));
+            }
+        }
+
+        segments
+    }
+}
+
+// A single bit as a line at the top (1) or bottom (0) joined by vertical edges,
+// a dashed line through the middle for z and a shaded band for x
+fn render_scalar(svg: &mut String, axis: &Axis, top: f64, changes: &[(u64, SignalValue)]) {
+    let bottom = top + TRACE_HEIGHT;
+    let middle = top + TRACE_HEIGHT / 2.0;
+    let mut path = String::new();
+    // Whether the path ends at the level of the previous segment
+    let mut connected = false;
+
+    for (first, last, value, _, _) in axis.segments(changes) {
+        let value = match value {
+            SignalValue::Scalar(value) => *value,
+            _ => Value::X,
+        };
+
+        match value {
+            Value::V0 | Value::V1 => {
+                let y = if value == Value::V1 { top } else { bottom };
+                if connected {
+                    let _ = write!(path, "V{} H{} ", y, last);
+                } else {
+                    let _ = write!(path, "M{} {} H{} ", first, y, last);
+                }
+                connected = true;
+            }
+            Value::Z => {
+                let _ = writeln!(
+                    svg,
+                    r#"<line x1="{}" y1="{m}" x2="{}" y2="{m}" stroke="{}" stroke-dasharray="3 2"/>"#,
+                    first,
+                    last,
+                    HIGH_IMPEDANCE,
+                    m = middle
+                );
+                connected = false;
+            }
+            Value::X => {
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{c}" fill-opacity="0.25" stroke="{c}"/>"#,
+                    first,
+                    top,
+                    last - first,
+                    TRACE_HEIGHT,
+                    c = UNKNOWN
+                );
+                connected = false;
+            }
+        }
+    }
+
+    if !path.is_empty() {
+        let _ = writeln!(
+            svg,
+            r#"<path d="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+            path.trim_end(),
+            TRACE
+        );
+    }
+}
+
+// A bus: a hexagon per value with the value printed inside when it fits.
+// Values with x bits are red, values with z bits yellow
+fn render_bus(
+    svg: &mut String,
+    axis: &Axis,
+    top: f64,
+    changes: &[(u64, SignalValue)],
+    radix: Radix,
+) {
+    let bottom = top + TRACE_HEIGHT;
+    let middle = top + TRACE_HEIGHT / 2.0;
+
+    for (first, last, value, starts, ends) in axis.segments(changes) {
+        let bits: Vec<Value> = match value {
+            SignalValue::Vector(vector) => vector.iter().collect(),
+            SignalValue::Scalar(value) => vec![*value],
+            _ => Vec::new(),
+        };
+        let color = if bits.contains(&Value::X) {
+            UNKNOWN
+        } else if bits.contains(&Value::Z) {
+            HIGH_IMPEDANCE
+        } else {
+            TRACE
+        };
+
+        if !bits.is_empty() && bits.iter().all(|bit| *bit == Value::Z) {
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{}" y1="{m}" x2="{}" y2="{m}" stroke="{}" stroke-dasharray="3 2"/>"#,
+                first,
+                last,
+                color,
+                m = middle
+            );
+            continue;
+        }
+
+        let slant = 4.0_f64.min((last - first) / 2.0);
+        let (left_slant, right_slant) = (
+            if starts { slant } else { 0.0 },
+            if ends { slant } else { 0.0 },
+        );
+        let fill = if color == UNKNOWN { "0.25" } else { "0" };
+        let _ = writeln!(
+            svg,
+            r#"<polygon points="{},{m} {},{t} {},{t} {},{m} {},{b} {},{b}" fill="{c}" fill-opacity="{}" stroke="{c}" stroke-width="1.5"/>"#,
+            first,
+            first + left_slant,
+            last - right_slant,
+            last,
+            last - right_slant,
+            first + left_slant,
+            fill,
+            m = middle,
+            t = top,
+            b = bottom,
+            c = color
+        );
+
+        let room = ((last - first - 2.0 * slant) / CHAR_WIDTH).floor() as usize;
+        let label = fit_label(&format_value(value, radix), room);
+        if !label.is_empty() {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" fill="{}" text-anchor="middle">{}</text>"#,
+                (first + last) / 2.0,
+                middle + 4.0,
+                TEXT,
+                escape(&label)
+            );
+        }
+    }
+}
+
+// A real signal as a line scaled to the values in the window, with the
+// maximum and minimum printed on the left
+fn render_real(
+    svg: &mut String,
+    axis: &Axis,
+    top: f64,
+    changes: &[(u64, SignalValue)],
+    interpolation: Interpolation,
+) {
+    let points = AnalogTrace::new(changes, axis.start, axis.end)
+        .interpolation(interpolation)
+        .points();
+    if points.is_empty() {
+        return;
+    }
+
+    let min = points.iter().map(|(_, v)| *v).fold(f64::INFINITY, f64::min);
+    let max = points
+        .iter()
+        .map(|(_, v)| *v)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (low, high) = if min < max {
+        (min, max)
+    } else {
+        (min - 1.0, max + 1.0)
+    };
+
+    let corners: Vec<String> = points
+        .iter()
+        .map(|(time, value)| {
+            let x = axis.left
+                + (time - axis.start as f64) / (axis.end - axis.start) as f64
+                    * (axis.right - axis.left);
+            let y = top + (high - value) / (high - low) * TRACE_HEIGHT;
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect();
+    let _ = writeln!(
+        svg,
+        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+        corners.join(" "),
+        TRACE
+    );
+
+    for (value, y) in [(max, top + 8.0), (min, top + TRACE_HEIGHT)] {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{}" font-size="9">{}</text>"#,
+            axis.left + 2.0,
+            y,
+            HIGH_IMPEDANCE,
+            format_real(value)
+        );
+    }
+}
+
+// A dashed vertical line at `time` over the rows, labeled above the ruler
+fn render_time_line(
+    svg: &mut String,
+    axis: &Axis,
+    time: u64,
+    label: &str,
+    color: &str,
+    height: f64,
+) {
+    if time < axis.start || time > axis.end {
+        return;
+    }
+
+    let x = axis.x(time);
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{x}" y1="4" x2="{x}" y2="{}" stroke="{c}" stroke-dasharray="4 3"/>"#,
+        height,
+        c = color
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="12" fill="{}">{}</text>"#,
+        x + 3.0,
+        color,
+        escape(label)
+    );
+}
+
+// Escape text for use in an SVG document
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::waveform::tests::load_fixture;
+
+    #[test]
+    fn documents_have_a_row_per_signal() {
+        let waveform = load_fixture("test6.vcd");
+        let signals = [
+            waveform.find_signal("cpu_tb.clk").unwrap(),
+            waveform.find_signal("dut.pc").unwrap(),
+        ];
+
+        let svg = SvgPlot::new(&waveform, &signals, 0, 40)
+            .cursor(Some(20))
+            .markers(&[('A', 10)])
+            .render();
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">cpu_tb.clk</text>"));
+        assert!(svg.contains(">cpu_tb.dut.pc</text>"));
+        // pc counts 00, 01, 02 and 03 over the window
+        assert_eq!(svg.matches("<polygon").count(), 4);
+        assert!(svg.contains(">02</text>"));
+        assert!(svg.contains(">20 ns</text>"));
+        assert!(svg.contains(">A</text>"));
+    }
+}
@@ -0,0 +1,69 @@
+use clap::ValueEnum;
+
+use ratatui::style::Color;
+
+// Color scheme of the Plot tab, picked with --theme
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Theme {
+    // Bright colors for dark terminal backgrounds
+    #[default]
+    Dark,
+    // Darker colors readable on light backgrounds
+    Light,
+    // The terminal's own foreground for everything
+    Mono,
+}
+
+// Colors used to draw signals, their names and values, and the time markers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub trace: Color,
+    pub label: Color,
+    pub text: Color,
+    pub unknown: Color,
+    pub high_impedance: Color,
+    pub name: Color,
+    pub value: Color,
+    pub cursor: Color,
+    pub marker: Color,
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Dark => Palette {
+                trace: Color::LightCyan,
+                label: Color::White,
+                text: Color::LightGreen,
+                unknown: Color::Red,
+                high_impedance: Color::Yellow,
+                name: Color::Cyan,
+                value: Color::Yellow,
+                cursor: Color::Yellow,
+                marker: Color::Magenta,
+            },
+            Theme::Light => Palette {
+                trace: Color::Blue,
+                label: Color::Black,
+                text: Color::Green,
+                unknown: Color::Red,
+                high_impedance: Color::Magenta,
+                name: Color::Blue,
+                value: Color::Black,
+                cursor: Color::Red,
+                marker: Color::Magenta,
+            },
+            Theme::Mono => Palette {
+                trace: Color::Reset,
+                label: Color::Reset,
+                text: Color::Reset,
+                unknown: Color::Reset,
+                high_impedance: Color::Reset,
+                name: Color::Reset,
+                value: Color::Reset,
+                cursor: Color::Reset,
+                marker: Color::Reset,
+            },
+        }
+    }
+}
@@ -0,0 +1,677 @@
+use ratatui::{
+    prelude::*,
+    widgets::{
+        canvas::{Canvas, Line as CanvasLine},
+        Widget,
+    },
+};
+
+use vcd::Value;
+
+use super::{
+    radix::Radix,
+    summary::{summarize, Column, RangeSummary},
+    theme::Palette,
+    waveform::SignalValue,
+};
+
+// Draws the value changes of one signal over the time window [start, end],
+// every terminal column covering an equal slice of time. Columns where the signal
+// changes more than once are filled in as a band of activity
+pub struct Trace<'a> {
+    changes: &'a [(u64, SignalValue)],
+    start: u64,
+    end: u64,
+    radix: Radix,
+    style: Style,
+    label_style: Style,
+    text_style: Style,
+    unknown_style: Style,
+    high_impedance_style: Style,
+}
+
+impl<'a> Trace<'a> {
+    pub fn new(changes: &'a [(u64, SignalValue)], start: u64, end: u64) -> Trace<'a> {
+        Trace {
+            changes,
+            start,
+            end,
+            radix: Radix::Hexadecimal,
+            style: Style::default().fg(Color::LightCyan),
+            label_style: Style::default().fg(Color::White),
+            text_style: Style::default().fg(Color::LightGreen),
+            unknown_style: Style::default().fg(Color::Red),
+            high_impedance_style: Style::default().fg(Color::Yellow),
+        }
+    }
+
+    pub fn palette(mut self, palette: Palette) -> Trace<'a> {
+        self.style = Style::default().fg(palette.trace);
+        self.label_style = Style::default().fg(palette.label);
+        self.text_style = Style::default().fg(palette.text);
+        self.unknown_style = Style::default().fg(palette.unknown);
+        self.high_impedance_style = Style::default().fg(palette.high_impedance);
+        self
+    }
+
+    pub fn radix(mut self, radix: Radix) -> Trace<'a> {
+        self.radix = radix;
+        self
+    }
+
+    // Visible (first column, last column + 1, value, starts with a change) runs of the
+    // trace. A run starts at every column with changes, with the value after the last one
+    fn segments(columns: &[Column<'a>]) -> Vec<(u16, u16, &'a SignalValue, bool)> {
+        let mut segments = Vec::new();
+        let mut current: Option<(u16, &SignalValue, bool)> = None;
+
+        for (x, column) in columns.iter().enumerate() {
+            let x = x as u16;
+            let start = match column.last {
+                Some(value) if column.transitions > 0 => Some((value, true)),
+                Some(value) if x == 0 => Some((value, false)),
+                _ => None,
+            };
+
+            if let Some((value, changed)) = start {
+                if let Some((first, value, changed)) = current.take() {
+                    segments.push((first, x, value, changed));
+                }
+                current = Some((x, value, changed));
+            }
+        }
+        if let Some((first, value, changed)) = current {
+            segments.push((first, columns.len() as u16, value, changed));
+        }
+
+        segments
+    }
+
+    // Fill the busy columns from top to bottom, colored after the value they hold the longest
+    fn render_activity(&self, area: Rect, buf: &mut Buffer, columns: &[Column]) {
+        for (x, column) in columns.iter().enumerate() {
+            if !column.is_busy() {
+                continue;
+            }
+
+            let style = match column.dominant {
+                Some(SignalValue::Scalar(Value::X)) => self.unknown_style,
+                Some(SignalValue::Scalar(Value::Z)) => self.high_impedance_style,
+                Some(SignalValue::Vector(vector)) if vector.iter().any(|bit| bit == Value::X) => {
+                    self.unknown_style
+                }
+                _ => self.style,
+            };
+            for y in area.top()..area.bottom() {
+                buf.get_mut(area.left() + x as u16, y)
+                    .set_symbol("█")
+                    .set_style(style);
+            }
+        }
+    }
+
+    fn label(&self, value: &SignalValue) -> String {
+        format_value(value, self.radix)
+    }
+
+    // Row of a scalar level: 1 at the top, 0 at the bottom, z and x in the middle
+    fn scalar_row(area: Rect, value: Value) -> u16 {
+        match value {
+            Value::V1 => area.top(),
+            Value::V0 => area.bottom() - 1,
+            Value::X | Value::Z => area.top() + (area.height - 1) / 2,
+        }
+    }
+
+    // Draw a single bit: lines at the top (1) or bottom (0) of the area joined by
+    // vertical edges, a dashed line through the middle for z and a hatched band for x
+    fn render_scalar(&self, area: Rect, buf: &mut Buffer, columns: &[Column<'a>]) {
+        let mut previous: Option<Value> = None;
+
+        for (first, last, value, changed) in Trace::segments(columns) {
+            let value = match value {
+                SignalValue::Scalar(value) => *value,
+                _ => Value::X,
+            };
+
+            for column in first..last {
+                let x = area.left() + column;
+
+                if value == Value::X {
+                    for y in area.top()..area.bottom() {
+                        buf.get_mut(x, y)
+                            .set_symbol("╱")
+                            .set_style(self.unknown_style);
+                    }
+                    continue;
+                }
+
+                let row = Trace::scalar_row(area, value);
+                let (symbol, style) = match value {
+                    Value::Z => ("┄", self.high_impedance_style),
+                    Value::V1 if area.height == 1 => ("▔", self.style),
+                    Value::V0 if area.height == 1 => ("▁", self.style),
+                    _ => ("─", self.style),
+                };
+                buf.get_mut(x, row).set_symbol(symbol).set_style(style);
+            }
+
+            // Join the previous level to this one with an edge in the first column
+            if let Some(from) = previous.filter(|_| changed) {
+                if from != Value::X && value != Value::X {
+                    self.render_edge(area, buf, area.left() + first, from, value);
+                }
+            }
+            previous = Some(value);
+        }
+    }
+
+    fn render_edge(&self, area: Rect, buf: &mut Buffer, x: u16, from: Value, to: Value) {
+        if area.height == 1 {
+            let symbol = match (from, to) {
+                (Value::V0, Value::V1) => "╱",
+                (Value::V1, Value::V0) => "╲",
+                _ => return,
+            };
+            buf.get_mut(x, area.top())
+                .set_symbol(symbol)
+                .set_style(self.style);
+            return;
+        }
+
+        let from_row = Trace::scalar_row(area, from);
+        let to_row = Trace::scalar_row(area, to);
+
+        if from_row == to_row {
+            return;
+        }
+
+        let rising = to_row < from_row;
+        for y in from_row.min(to_row)..=from_row.max(to_row) {
+            let symbol = if y == from_row {
+                if rising {
+                    "┘"
+                } else {
+                    "┐"
+                }
+            } else if y == to_row {
+                if rising {
+                    "┌"
+                } else {
+                    "└"
+                }
+            } else {
+                "│"
+            };
+            buf.get_mut(x, y).set_symbol(symbol).set_style(self.style);
+        }
+    }
+
+    // Draw a bus: a line above and below every segment, a bracket at every change
+    // and the value (or text of a string signal) printed inside the segment, with
+    // an ellipsis when it does not fit. Unknown values are drawn as a red
+    // hatched band, high impedance values as a dashed line through the middle
+    fn render_bus(&self, area: Rect, buf: &mut Buffer, columns: &[Column<'a>]) {
+        let top = area.top();
+        let bottom = area.bottom() - 1;
+        let middle = top + (area.height - 1) / 2;
+
+        for (first, last, value, changed) in Trace::segments(columns) {
+            let bits: Vec<Value> = match value {
+                SignalValue::Vector(vector) => vector.iter().collect(),
+                SignalValue::Scalar(value) => vec![*value],
+                _ => Vec::new(),
+            };
+            let all_unknown = !bits.is_empty() && bits.iter().all(|bit| *bit == Value::X);
+            let all_high_impedance = !bits.is_empty() && bits.iter().all(|bit| *bit == Value::Z);
+
+            // Buses with some x or z bits keep their shape but change color
+            let (style, label_style) = if bits.contains(&Value::X) {
+                (self.unknown_style, self.unknown_style)
+            } else if bits.contains(&Value::Z) {
+                (self.high_impedance_style, self.high_impedance_style)
+            } else if let SignalValue::String(_) = value {
+                (self.style, self.text_style)
+            } else {
+                (self.style, self.label_style)
+            };
+
+            for column in first..last {
+                let x = area.left() + column;
+                let boundary = changed && column == first;
+
+                if all_high_impedance {
+                    let symbol = if boundary { "│" } else { "┄" };
+                    buf.get_mut(x, middle).set_symbol(symbol).set_style(style);
+                    continue;
+                }
+
+                for y in top..=bottom {
+                    let symbol = if area.height == 1 {
+                        if boundary {
+                            "╳"
+                        } else {
+                            "─"
+                        }
+                    } else if y == top && area.height > 2 {
+                        if boundary {
+                            "┬"
+                        } else {
+                            "─"
+                        }
+                    } else if y == bottom {
+                        if boundary {
+                            "┴"
+                        } else {
+                            "─"
+                        }
+                    } else if boundary {
+                        "│"
+                    } else if all_unknown {
+                        "╱"
+                    } else {
+                        " "
+                    };
+                    buf.get_mut(x, y).set_symbol(symbol).set_style(style);
+                }
+            }
+
+            // The label sits on the middle row, or over the line when there is only one row
+            let label_first = if changed { first + 1 } else { first };
+            if label_first < last {
+                let label = fit_label(&self.label(value), (last - label_first) as usize);
+                buf.set_string(
+                    area.left() + label_first,
+                    if area.height == 2 && !all_high_impedance {
+                        top
+                    } else {
+                        middle
+                    },
+                    label,
+                    label_style,
+                );
+            }
+        }
+    }
+}
+
+impl Widget for Trace<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+
+        let columns = summarize(self.changes, self.start, self.end, area.width);
+        match self.changes.first() {
+            Some((_, SignalValue::Scalar(_))) => self.render_scalar(area, buf, &columns),
+            Some(_) => self.render_bus(area, buf, &columns),
+            None => {}
+        }
+        self.render_activity(area, buf, &columns);
+    }
+}
+
+// How a real signal moves between two changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    // Hold every value until the next change
+    Step,
+    // Draw a straight line from every value to the next
+    Linear,
+}
+
+impl Interpolation {
+    pub fn toggle(self) -> Interpolation {
+        match self {
+            Interpolation::Step => Interpolation::Linear,
+            Interpolation::Linear => Interpolation::Step,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpolation::Step => "step",
+            Interpolation::Linear => "linear",
+        }
+    }
+}
+
+// Draws a real signal over the time window [start, end] as an analog line,
+// scaled vertically to the values visible in the window
+pub struct AnalogTrace<'a> {
+    changes: &'a [(u64, SignalValue)],
+    start: u64,
+    end: u64,
+    interpolation: Interpolation,
+    // Ranges of the values, to draw windows with more changes than columns quickly
+    summary: Option<&'a RangeSummary>,
+    color: Color,
+    label_style: Style,
+}
+
+impl<'a> AnalogTrace<'a> {
+    pub fn new(changes: &'a [(u64, SignalValue)], start: u64, end: u64) -> AnalogTrace<'a> {
+        AnalogTrace {
+            changes,
+            start,
+            end,
+            interpolation: Interpolation::Step,
+            summary: None,
+            color: Color::LightCyan,
+            label_style: Style::default().fg(Color::Yellow),
+        }
+    }
+
+    pub fn summary(mut self, summary: Option<&'a RangeSummary>) -> AnalogTrace<'a> {
+        self.summary = summary;
+        self
+    }
+
+    pub fn palette(mut self, palette: Palette) -> AnalogTrace<'a> {
+        self.color = palette.trace;
+        self.label_style = Style::default().fg(palette.value);
+        self
+    }
+
+    pub fn interpolation(mut self, interpolation: Interpolation) -> AnalogTrace<'a> {
+        self.interpolation = interpolation;
+        self
+    }
+
+    // (time, value) of the real changes the window shows: the last one before it,
+    // those within it and the first one after it, found without going through the others
+    fn values(&self) -> Vec<(f64, f64)> {
+        let first = self
+            .changes
+            .partition_point(|(time, _)| *time <= self.start)
+            .saturating_sub(1);
+        let last = (self.changes.partition_point(|(time, _)| *time < self.end) + 1)
+            .min(self.changes.len());
+
+        self.changes[first..last]
+            .iter()
+            .filter_map(|(time, value)| match value {
+                SignalValue::Real(value) => Some((*time as f64, *value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Value at `time`, None before the first change
+    fn value_at(&self, values: &[(f64, f64)], time: f64) -> Option<f64> {
+        let position = values.partition_point(|(t, _)| *t <= time);
+
+        if position == 0 {
+            return None;
+        }
+
+        let (t0, v0) = values[position - 1];
+        match (self.interpolation, values.get(position)) {
+            (Interpolation::Linear, Some((t1, v1))) => {
+                Some(v0 + (v1 - v0) * (time - t0) / (t1 - t0))
+            }
+            _ => Some(v0),
+        }
+    }
+
+    // Corners of the line drawn inside the window, clipped at its edges
+    pub fn points(&self) -> Vec<(f64, f64)> {
+        let values = self.values();
+        let (start, end) = (self.start as f64, self.end as f64);
+
+        let mut points = Vec::new();
+        if let Some(value) = self.value_at(&values, start) {
+            points.push((start, value));
+        }
+
+        for (time, value) in values.iter() {
+            if *time <= start || *time >= end {
+                continue;
+            }
+            if let (Interpolation::Step, Some(&(_, previous))) = (self.interpolation, points.last())
+            {
+                points.push((*time, previous));
+            }
+            points.push((*time, *value));
+        }
+
+        if let Some(value) = self.value_at(&values, end) {
+            points.push((end, value));
+        }
+
+        points
+    }
+}
+
+impl Widget for AnalogTrace<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+
+        // Every column is drawn as a bar over the values it goes through when there
+        // are more changes than columns, instead of a line between every change
+        let mut bars = Vec::new();
+        if let Some(summary) = self.summary {
+            let columns = summarize(self.changes, self.start, self.end, area.width);
+            if columns.iter().any(Column::is_busy) {
+                let span = self.end.saturating_sub(self.start).max(1) as f64;
+                for (x, column) in columns.iter().enumerate() {
+                    // A linear line heads for the first change after the column
+                    let (first, mut last) = column.changes;
+                    if self.interpolation == Interpolation::Linear {
+                        last = (last + 1).min(self.changes.len());
+                    }
+                    if let Some((low, high)) = summary.range(self.changes, first, last) {
+                        let time = self.start as f64 + (x as f64 + 0.5) * span / area.width as f64;
+                        bars.push((time, low, high));
+                    }
+                }
+            }
+        }
+
+        let points = if bars.is_empty() {
+            self.points()
+        } else {
+            Vec::new()
+        };
+        let values = points
+            .iter()
+            .map(|(_, v)| (*v, *v))
+            .chain(bars.iter().map(|(_, low, high)| (*low, *high)));
+        let (min, max) = values.fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(min, max), (low, high)| (min.min(low), max.max(high)),
+        );
+        if min > max {
+            return;
+        }
+
+        // A flat line is drawn in the middle of the area
+        let (low, high) = if min < max {
+            (min, max)
+        } else {
+            (min - 1.0, max + 1.0)
+        };
+
+        let canvas = Canvas::default()
+            .marker(symbols::Marker::Braille)
+            .x_bounds([self.start as f64, self.end.max(self.start + 1) as f64])
+            .y_bounds([low, high])
+            .paint(|ctx| {
+                for (time, low, high) in bars.iter() {
+                    ctx.draw(&CanvasLine {
+                        x1: *time,
+                        y1: *low,
+                        x2: *time,
+                        y2: *high,
+                        color: self.color,
+                    });
+                }
+                for pair in points.windows(2) {
+                    ctx.draw(&CanvasLine {
+                        x1: pair[0].0,
+                        y1: pair[0].1,
+                        x2: pair[1].0,
+                        y2: pair[1].1,
+                        color: self.color,
+                    });
+                }
+            });
+        canvas.render(area, buf);
+
+        // Maximum and minimum of the visible values in the top and bottom left corners
+        buf.set_string(area.left(), area.top(), format_real(max), self.label_style);
+        if area.height > 1 {
+            buf.set_string(
+                area.left(),
+                area.bottom() - 1,
+                format_real(min),
+                self.label_style,
+            );
+        }
+    }
+}
+
+// Text of a value, vectors printed in `radix`
+pub fn format_value(value: &SignalValue, radix: Radix) -> String {
+    match value {
+        SignalValue::Vector(vector) => {
+            let bits: Vec<Value> = vector.iter().collect();
+            radix.format(&bits)
+        }
+        SignalValue::Scalar(value) => value.to_string(),
+        SignalValue::Real(value) => format_real(*value),
+        SignalValue::String(value) => value.clone(),
+    }
+}
+
+// Print a real value with at most 4 decimals and no trailing zeros
+pub fn format_real(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+// Shorten `text` to `width` characters, ending with an ellipsis when it does not fit
+pub fn fit_label(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else if width == 0 {
+        String::new()
+    } else {
+        let mut label: String = text.chars().take(width - 1).collect();
+        label.push('…');
+        label
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::waveform::tests::load_fixture;
+
+    fn render(name: &str, reference: &str, width: u16, height: u16) -> Vec<String> {
+        let waveform = load_fixture(name);
+        let signal = waveform
+            .signals
+            .iter()
+            .find(|signal| signal.reference == reference)
+            .unwrap();
+
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        Trace::new(
+            waveform.signal_changes(signal),
+            0,
+            waveform.last_timestamp(),
+        )
+        .render(area, &mut buf);
+
+        (0..height)
+            .map(|y| (0..width).map(|x| buf.get(x, y).symbol.clone()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn scalar_edges_are_drawn_at_the_change_time() {
+        assert_eq!(
+            render("test2.vcd", "sel", 10, 3),
+            vec!["     ┌────", "     │    ", "─────┘    "]
+        );
+        assert_eq!(render("test2.vcd", "out", 10, 1), vec!["▔▔▔▔▔╲▁▁▁▁"]);
+    }
+
+    #[test]
+    fn unknown_and_high_impedance_are_drawn_distinctly() {
+        assert_eq!(render("test3.vcd", "tx_en", 10, 1), vec!["╱╱╱╱╱╱╱╱╱▁"]);
+        assert_eq!(
+            render("test.vcd", "n_mul", 10, 3),
+            vec!["          ", "┄┄┄┄┄┄┄┄┄┄", "          "]
+        );
+    }
+
+    #[test]
+    fn bus_values_are_printed_inside_segments() {
+        assert_eq!(
+            render("test3.vcd", "data", 10, 3),
+            vec!["─────────┬", "81       │", "─────────┴"]
+        );
+    }
+
+    #[test]
+    fn columns_changing_many_times_are_filled() {
+        // Toggling at every timestamp until 500
+        let changes: Vec<(u64, SignalValue)> = (0..=500)
+            .map(|time| {
+                let value = if time % 2 == 1 { Value::V1 } else { Value::V0 };
+                (time, SignalValue::Scalar(value))
+            })
+            .collect();
+
+        let area = Rect::new(0, 0, 10, 1);
+        let mut buf = Buffer::empty(area);
+        Trace::new(&changes, 0, 1000).render(area, &mut buf);
+        let row: String = (0..10).map(|x| buf.get(x, 0).symbol.clone()).collect();
+        assert_eq!(row, "█████╲▁▁▁▁");
+    }
+
+    #[test]
+    fn real_values_are_interpolated_between_changes() {
+        let changes = vec![
+            (0, SignalValue::Real(0.0)),
+            (10, SignalValue::Real(1.0)),
+            (20, SignalValue::Real(-1.0)),
+        ];
+
+        let step = AnalogTrace::new(&changes, 5, 15);
+        assert_eq!(
+            step.points(),
+            vec![(5.0, 0.0), (10.0, 0.0), (10.0, 1.0), (15.0, 1.0)]
+        );
+
+        let linear = AnalogTrace::new(&changes, 5, 15).interpolation(Interpolation::Linear);
+        assert_eq!(linear.points(), vec![(5.0, 0.5), (10.0, 1.0), (15.0, 0.0)]);
+
+        // Windows starting or ending at a change, and past the last one
+        let edges = AnalogTrace::new(&changes, 10, 20);
+        assert_eq!(edges.points(), vec![(10.0, 1.0), (20.0, -1.0)]);
+        let after = AnalogTrace::new(&changes, 25, 30).interpolation(Interpolation::Linear);
+        assert_eq!(after.points(), vec![(25.0, -1.0), (30.0, -1.0)]);
+    }
+
+    #[test]
+    fn strings_are_truncated_with_an_ellipsis() {
+        // START and DATA share the sixth column
+        assert_eq!(
+            render("test5.vcd", "state", 30, 1),
+            vec!["IDLE─█DA…█╳IDLE─────█DA…█IDLE─"]
+        );
+    }
+}
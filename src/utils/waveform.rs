@@ -0,0 +1,576 @@
+use vcd::{
+    Header, IdCode, ReferenceIndex, ScopeItem, ScopeType, TimescaleUnit, Value, VarType, Vector,
+};
+
+use std::{collections::HashMap, error::Error, fs, path::Path};
+
+use super::{
+    fst,
+    loader::{Batch, Loader},
+    source::Source,
+    summary::RangeSummary,
+    wavejson,
+};
+
+// A variable declared in the VCD header
+pub struct Signal {
+    pub var_type: VarType,
+    pub size: u32,
+    pub reference: String,
+    pub index: Option<ReferenceIndex>,
+    pub code: IdCode,
+    // Scope the variable is declared in, None at the top level
+    pub scope: Option<usize>,
+}
+
+// A $scope of the design hierarchy, with indexes into Waveform::scopes and Waveform::signals
+pub struct ScopeNode {
+    pub scope_type: ScopeType,
+    pub name: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub signals: Vec<usize>,
+}
+
+// A value taken by a signal after a change
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignalValue {
+    Scalar(Value),
+    Vector(Vector),
+    Real(f64),
+    String(String),
+}
+
+// Kind of transition looked for when jumping between changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    // Any change to a different value
+    Any,
+    // A scalar going to 1, or a vector changing value
+    Rising,
+    // A scalar going to 0, or a vector changing value
+    Falling,
+}
+
+impl Edge {
+    fn matches(self, from: &SignalValue, to: &SignalValue) -> bool {
+        if from == to {
+            return false;
+        }
+
+        match (self, to) {
+            (Edge::Rising, SignalValue::Scalar(value)) => *value == Value::V1,
+            (Edge::Falling, SignalValue::Scalar(value)) => *value == Value::V0,
+            _ => true,
+        }
+    }
+}
+
+// In-memory model of a VCD file, filled in as it is read and rendered by every tab
+#[derive(Default)]
+pub struct Waveform {
+    pub version: Option<String>,
+    pub date: Option<String>,
+    pub timescale: Option<(u32, TimescaleUnit)>,
+    // Every scope of the hierarchy, parents before their children
+    pub scopes: Vec<ScopeNode>,
+    pub top_scopes: Vec<usize>,
+    // Every variable of every scope, and those declared outside of any scope
+    pub signals: Vec<Signal>,
+    pub top_signals: Vec<usize>,
+    // Value changes of every id code as (timestamp, value), sorted by timestamp
+    // with at most one value per timestamp
+    pub changes: HashMap<IdCode, Vec<(u64, SignalValue)>>,
+    pub timestamps: Vec<u64>,
+    // Ranges of the values of real signals, to draw them zoomed out quickly
+    pub summaries: HashMap<IdCode, RangeSummary>,
+    // Human readable line per command, shown on the Parser tab
+    pub parser_log: Vec<String>,
+    // Text of the file, shown on the VCD Code tab
+    pub source: Source,
+}
+
+impl Waveform {
+    // Read and parse the VCD file at `path` (stdin for "-", possibly compressed), the
+    // WaveDrom timing diagram of a .json file or the FST file of a .fst one
+    pub fn load(path: impl AsRef<Path>) -> Result<Waveform, Box<dyn Error>> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|extension| extension == "fst") {
+            return fst::load(path);
+        }
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            let source =
+                fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            return wavejson::parse(&source);
+        }
+
+        let (mut waveform, mut loader) = Loader::open(path)?;
+        loader.finish(&mut waveform)?;
+        Ok(waveform)
+    }
+
+    // Waveform declared by a VCD header, before any change is read
+    pub fn from_header(header: Header) -> Waveform {
+        let mut waveform = Waveform {
+            version: header.version,
+            date: header.date,
+            timescale: header.timescale,
+            ..Waveform::default()
+        };
+        waveform.add_scope_items(&header.items, None);
+
+        for signal in waveform.signals.iter() {
+            waveform.changes.insert(signal.code, Vec::new());
+            if signal.var_type == VarType::Real {
+                waveform
+                    .summaries
+                    .insert(signal.code, RangeSummary::default());
+            }
+        }
+        waveform
+    }
+
+    // Add the changes read by a loader, which follow those already added
+    pub fn add_batch(&mut self, batch: Batch) {
+        for (id, time, value) in batch.changes {
+            self.push_change(id, time, value);
+        }
+        self.timestamps.extend(batch.timestamps);
+        self.parser_log.extend(batch.log);
+        for (code, summary) in self.summaries.iter_mut() {
+            summary.update(&self.changes[code]);
+        }
+
+        match &mut self.source {
+            Source::File {
+                line_starts, lines, ..
+            } => {
+                line_starts.extend(batch.line_starts);
+                *lines = batch.lines;
+            }
+            Source::Text(text) => text.push_str(&batch.text),
+        }
+    }
+
+    // Add the scopes and variables of a header scope (or of the header itself) to the hierarchy
+    fn add_scope_items(&mut self, items: &[ScopeItem], parent: Option<usize>) {
+        for item in items {
+            match item {
+                ScopeItem::Scope(scope) => {
+                    let index = self.scopes.len();
+                    self.scopes.push(ScopeNode {
+                        scope_type: scope.scope_type,
+                        name: scope.identifier.clone(),
+                        parent,
+                        children: Vec::new(),
+                        signals: Vec::new(),
+                    });
+                    match parent {
+                        Some(parent) => self.scopes[parent].children.push(index),
+                        None => self.top_scopes.push(index),
+                    }
+                    self.add_scope_items(&scope.items, Some(index));
+                }
+                ScopeItem::Var(v) => {
+                    let index = self.signals.len();
+                    self.signals.push(Signal {
+                        var_type: v.var_type,
+                        size: v.size,
+                        reference: v.reference.clone(),
+                        index: v.index,
+                        code: v.code,
+                        scope: parent,
+                    });
+                    match parent {
+                        Some(parent) => self.scopes[parent].signals.push(index),
+                        None => self.top_signals.push(index),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Add the scopes and signals of `other` under a new top level scope named `name`,
+    // with id codes of their own, and return the index of that scope. Changes keep
+    // their times and the timestamps stay those of this waveform
+    pub fn add_overlay(&mut self, other: Waveform, name: &str) -> usize {
+        let top = self.scopes.len();
+        let (scope_offset, signal_offset) = (top + 1, self.signals.len());
+        let moved = |scope: Option<usize>| Some(scope.map_or(top, |scope| scope + scope_offset));
+
+        self.scopes.push(ScopeNode {
+            scope_type: ScopeType::Module,
+            name: name.to_string(),
+            parent: None,
+            children: other
+                .top_scopes
+                .iter()
+                .map(|scope| scope + scope_offset)
+                .collect(),
+            signals: other
+                .top_signals
+                .iter()
+                .map(|signal| signal + signal_offset)
+                .collect(),
+        });
+        self.top_scopes.push(top);
+        for scope in other.scopes {
+            self.scopes.push(ScopeNode {
+                parent: moved(scope.parent),
+                children: scope
+                    .children
+                    .iter()
+                    .map(|child| child + scope_offset)
+                    .collect(),
+                signals: scope
+                    .signals
+                    .iter()
+                    .map(|signal| signal + signal_offset)
+                    .collect(),
+                ..scope
+            });
+        }
+
+        let mut code = IdCode::FIRST;
+        for mut signal in other.signals {
+            let changes = other.changes.get(&signal.code).cloned().unwrap_or_default();
+            while self.changes.contains_key(&code) {
+                code = code.next();
+            }
+            if signal.var_type == VarType::Real {
+                let mut summary = RangeSummary::default();
+                summary.update(&changes);
+                self.summaries.insert(code, summary);
+            }
+            self.changes.insert(code, changes);
+            signal.code = code;
+            signal.scope = moved(signal.scope);
+            self.signals.push(signal);
+        }
+
+        top
+    }
+
+    // Add a signal computed from others, named `name` outside of any scope,
+    // and return its index in Waveform::signals
+    pub fn add_derived_signal(&mut self, name: &str, changes: Vec<(u64, SignalValue)>) -> usize {
+        let mut code = IdCode::FIRST;
+        while self.changes.contains_key(&code) {
+            code = code.next();
+        }
+
+        let size = match changes.first() {
+            Some((_, SignalValue::Vector(vector))) => vector.len() as u32,
+            _ => 1,
+        };
+        self.signals.push(Signal {
+            var_type: VarType::Wire,
+            size,
+            reference: name.to_string(),
+            index: None,
+            code,
+            scope: None,
+        });
+        self.changes.insert(code, changes);
+
+        self.signals.len() - 1
+    }
+
+    // Dotted hierarchical name of a scope, e.g. tb.dut.alu
+    pub fn scope_path(&self, scope: usize) -> String {
+        let node = &self.scopes[scope];
+        match node.parent {
+            Some(parent) => format!("{}.{}", self.scope_path(parent), node.name),
+            None => node.name.clone(),
+        }
+    }
+
+    // Dotted hierarchical name of a signal, e.g. tb.dut.alu.out
+    pub fn signal_path(&self, signal: &Signal) -> String {
+        match signal.scope {
+            Some(scope) => format!("{}.{}", self.scope_path(scope), signal.reference),
+            None => signal.reference.clone(),
+        }
+    }
+
+    fn push_change(&mut self, id: IdCode, time: u64, value: SignalValue) {
+        let changes = self.changes.entry(id).or_default();
+
+        // Only the last change at a timestamp is visible, e.g. an `x` from $dumpvars
+        // followed by a `0` at #0 leaves the signal at 0 from time 0
+        match changes.last_mut() {
+            Some(last) if last.0 == time => last.1 = value,
+            _ => changes.push((time, value)),
+        }
+    }
+
+    // Index of the signal with the given hierarchical path, or else the first
+    // one whose name or path ends with `name`
+    pub fn find_signal(&self, name: &str) -> Option<usize> {
+        let suffix = format!(".{}", name);
+        let paths: Vec<String> = self.signals.iter().map(|s| self.signal_path(s)).collect();
+
+        paths
+            .iter()
+            .position(|path| path == name)
+            .or_else(|| paths.iter().position(|path| path.ends_with(&suffix)))
+    }
+
+    // Value changes of a signal, empty if it never changes
+    pub fn signal_changes(&self, signal: &Signal) -> &[(u64, SignalValue)] {
+        self.changes
+            .get(&signal.code)
+            .map(|changes| changes.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn summary(&self, signal: &Signal) -> Option<&RangeSummary> {
+        self.summaries.get(&signal.code)
+    }
+
+    // Value of a signal at `time`, None before its first change
+    pub fn value_at(&self, signal: &Signal, time: u64) -> Option<&SignalValue> {
+        let changes = self.signal_changes(signal);
+        let index = changes.partition_point(|(change_time, _)| *change_time <= time);
+        index.checked_sub(1).map(|index| &changes[index].1)
+    }
+
+    // First transition of a signal strictly after `time`
+    pub fn next_edge(&self, signal: &Signal, time: u64, edge: Edge) -> Option<u64> {
+        let changes = self.signal_changes(signal);
+        let first = changes.partition_point(|(change_time, _)| *change_time <= time);
+
+        (first.max(1)..changes.len())
+            .find(|i| edge.matches(&changes[i - 1].1, &changes[*i].1))
+            .map(|i| changes[i].0)
+    }
+
+    // Last transition of a signal strictly before `time`
+    pub fn previous_edge(&self, signal: &Signal, time: u64, edge: Edge) -> Option<u64> {
+        let changes = self.signal_changes(signal);
+        let last = changes.partition_point(|(change_time, _)| *change_time < time);
+
+        (1..last)
+            .rev()
+            .find(|i| edge.matches(&changes[i - 1].1, &changes[*i].1))
+            .map(|i| changes[i].0)
+    }
+
+    pub fn last_timestamp(&self) -> u64 {
+        self.timestamps.last().copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // Path and waveform of a file of test_vcds, shared by the tests of every module
+    pub fn fixture_path(name: &str) -> String {
+        format!("{}/test_vcds/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    pub fn load_fixture(name: &str) -> Waveform {
+        Waveform::load(fixture_path(name)).unwrap()
+    }
+
+    fn signal<'a>(waveform: &'a Waveform, reference: &str) -> &'a Signal {
+        waveform
+            .signals
+            .iter()
+            .find(|signal| signal.reference == reference)
+            .unwrap()
+    }
+
+    fn scalars(waveform: &Waveform, reference: &str) -> Vec<(u64, Value)> {
+        waveform
+            .signal_changes(signal(waveform, reference))
+            .iter()
+            .map(|(time, value)| match value {
+                SignalValue::Scalar(v) => (*time, *v),
+                other => panic!("{reference} is not a scalar: {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn headers_scopes_and_changes_are_modelled() {
+        let waveform = load_fixture("test5.vcd");
+
+        assert_eq!(waveform.version.as_deref(), Some("Verilator 5.012"));
+        assert_eq!(waveform.timescale, Some((10, TimescaleUnit::PS)));
+        assert_eq!(waveform.top_scopes, vec![0]);
+        assert_eq!(waveform.scopes[0].name, "uart_tb");
+        let paths: Vec<String> = waveform
+            .signals
+            .iter()
+            .map(|signal| waveform.signal_path(signal))
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["uart_tb.state", "uart_tb.txn", "uart_tb.tx", "uart_tb.byte"]
+        );
+        assert_eq!(
+            waveform.timestamps,
+            vec![0, 100, 110, 120, 150, 180, 190, 200, 400, 410, 480, 490, 600]
+        );
+
+        let byte = signal(&waveform, "byte");
+        assert_eq!((byte.size, byte.index.is_some()), (8, true));
+        let values: Vec<(u64, String)> = waveform
+            .signal_changes(byte)
+            .iter()
+            .map(|(time, value)| match value {
+                SignalValue::Vector(v) => (*time, v.to_string()),
+                other => panic!("byte is not a vector: {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (0, "xxxxxxxx".to_string()),
+                (100, "01000001".to_string()),
+                (400, "00001010".to_string())
+            ]
+        );
+        assert_eq!(
+            waveform.value_at(signal(&waveform, "state"), 185),
+            Some(&SignalValue::String("PARITY".to_string()))
+        );
+        assert_eq!(
+            scalars(&waveform, "tx"),
+            vec![
+                (0, Value::V1),
+                (100, Value::V0),
+                (110, Value::V1),
+                (120, Value::V0),
+                (150, Value::V1),
+                (400, Value::V0)
+            ]
+        );
+    }
+
+    #[test]
+    fn changes_use_the_current_timestamp() {
+        let waveform = load_fixture("test2.vcd");
+
+        assert_eq!(waveform.timestamps, vec![0, 5, 10]);
+        assert_eq!(
+            scalars(&waveform, "out"),
+            vec![(0, Value::V1), (5, Value::V0)]
+        );
+        assert_eq!(
+            scalars(&waveform, "sel"),
+            vec![(0, Value::V0), (5, Value::V1)]
+        );
+        assert_eq!(scalars(&waveform, "a"), vec![(0, Value::V1)]);
+    }
+
+    #[test]
+    fn signals_changing_at_different_times_are_not_shuffled() {
+        let waveform = load_fixture("test3.vcd");
+
+        assert_eq!(
+            scalars(&waveform, "tx_en"),
+            vec![(0, Value::X), (2211, Value::V0)]
+        );
+        assert_eq!(
+            scalars(&waveform, "data_valid"),
+            vec![(0, Value::V0), (2296, Value::V1), (2302, Value::V0)]
+        );
+
+        let data = waveform.signal_changes(signal(&waveform, "data"));
+        let times: Vec<u64> = data.iter().map(|(time, _)| *time).collect();
+        assert_eq!(times, vec![0, 2296]);
+        assert_eq!(data[1].1, SignalValue::Vector("00000000".parse().unwrap()));
+    }
+
+    #[test]
+    fn dumpvars_values_start_at_time_zero() {
+        for name in ["test.vcd", "test1.vcd"] {
+            let waveform = load_fixture(name);
+
+            assert_eq!(scalars(&waveform, "rst"), vec![(0, Value::X)]);
+
+            let clk = scalars(&waveform, "clk");
+            assert_eq!(clk.len(), 20);
+            for (i, (time, value)) in clk.iter().enumerate() {
+                assert_eq!(*time, i as u64 * 5);
+                assert_eq!(*value, if i % 2 == 0 { Value::V0 } else { Value::V1 });
+            }
+        }
+    }
+
+    #[test]
+    fn nested_scopes_are_modelled() {
+        let waveform = load_fixture("test6.vcd");
+
+        assert_eq!(waveform.top_scopes, vec![0]);
+        assert_eq!(waveform.scopes.len(), 4);
+        assert_eq!(waveform.signals.len(), 11);
+        assert_eq!(waveform.scope_path(2), "cpu_tb.dut.alu");
+
+        let dut = &waveform.scopes[1];
+        assert_eq!(dut.children, vec![2, 3]);
+        let names: Vec<String> = dut
+            .signals
+            .iter()
+            .map(|signal| waveform.signal_path(&waveform.signals[*signal]))
+            .collect();
+        assert_eq!(
+            names,
+            vec!["cpu_tb.dut.clk", "cpu_tb.dut.rst_n", "cpu_tb.dut.pc"]
+        );
+
+        // Aliased variables share the changes of their id code
+        let carry = &waveform.signals[waveform.scopes[2].signals[3]];
+        assert_eq!(carry.reference, "carry");
+        assert_eq!(waveform.signal_changes(carry).len(), 3);
+        assert_eq!(
+            waveform.signal_changes(&waveform.signals[0]),
+            waveform.signal_changes(&waveform.signals[2])
+        );
+    }
+
+    #[test]
+    fn values_are_held_until_the_next_change() {
+        let waveform = load_fixture("test2.vcd");
+        let sel = signal(&waveform, "sel");
+        let first_change = scalars(&waveform, "sel")[1].0;
+
+        assert_eq!(
+            waveform.value_at(sel, first_change - 1),
+            Some(&SignalValue::Scalar(Value::V0))
+        );
+        assert_eq!(
+            waveform.value_at(sel, first_change),
+            Some(&SignalValue::Scalar(Value::V1))
+        );
+        assert_eq!(
+            waveform.value_at(sel, u64::MAX),
+            Some(&SignalValue::Scalar(Value::V1))
+        );
+    }
+
+    #[test]
+    fn edges_are_found_on_either_side_of_a_time() {
+        let waveform = load_fixture("test6.vcd");
+        let clk = signal(&waveform, "clk");
+        let pc = signal(&waveform, "pc");
+
+        assert_eq!(waveform.next_edge(clk, 0, Edge::Any), Some(5));
+        assert_eq!(waveform.next_edge(clk, 5, Edge::Falling), Some(10));
+        assert_eq!(waveform.next_edge(clk, 5, Edge::Rising), Some(15));
+        assert_eq!(waveform.previous_edge(clk, 15, Edge::Rising), Some(5));
+        assert_eq!(waveform.previous_edge(clk, 5, Edge::Any), None);
+        assert_eq!(waveform.next_edge(pc, 15, Edge::Rising), Some(25));
+        assert_eq!(
+            waveform.next_edge(clk, waveform.last_timestamp(), Edge::Any),
+            None
+        );
+    }
+}
@@ -0,0 +1,545 @@
+use serde_json::{json, Value as Json};
+
+use vcd::{IdCode, ScopeType, TimescaleUnit, Value, VarType};
+
+use std::error::Error;
+
+use super::{
+    radix::Radix,
+    ruler::{format_time, parse_time},
+    search::{parse_literal, resize, value_bits},
+    source::Source,
+    trace::format_value,
+    waveform::{ScopeNode, Signal, SignalValue, Waveform},
+};
+
+// Timestamps taken by one character of a wave read from WaveJSON, even so that
+// clocks can fall halfway through a period
+const TICKS_PER_PERIOD: u64 = 4;
+
+// Longest wave written to WaveJSON, longer windows are sampled more coarsely
+const MAX_PERIODS: u64 = 1000;
+
+// Signals of a waveform over a time window as a WaveDrom timing diagram. Every
+// character of a wave is one period, sampled at its start
+pub struct WaveJson<'a> {
+    waveform: &'a Waveform,
+    // Indexes into Waveform::signals, one wave each
+    signals: &'a [usize],
+    start: u64,
+    end: u64,
+    // Per signal settings indexed like Waveform::signals, `radix` for the missing ones
+    radix: Radix,
+    radixes: &'a [Radix],
+    // Timestamps per character, None to fit every change of the window
+    period: Option<u64>,
+}
+
+impl<'a> WaveJson<'a> {
+    pub fn new(waveform: &'a Waveform, signals: &'a [usize], start: u64, end: u64) -> WaveJson<'a> {
+        WaveJson {
+            waveform,
+            signals,
+            start,
+            end,
+            radix: Radix::Hexadecimal,
+            radixes: &[],
+            period: None,
+        }
+    }
+
+    pub fn radix(mut self, radix: Radix) -> WaveJson<'a> {
+        self.radix = radix;
+        self
+    }
+
+    pub fn radixes(mut self, radixes: &'a [Radix]) -> WaveJson<'a> {
+        self.radixes = radixes;
+        self
+    }
+
+    pub fn period(mut self, period: Option<u64>) -> WaveJson<'a> {
+        self.period = period;
+        self
+    }
+
+    // Greatest period that starts a character at every change in the window,
+    // widened to keep the wave under MAX_PERIODS characters
+    fn auto_period(&self) -> u64 {
+        let span = self.end.saturating_sub(self.start).max(1);
+        let mut period = span;
+
+        for index in self.signals.iter() {
+            let signal = &self.waveform.signals[*index];
+            for (time, _) in self.waveform.signal_changes(signal) {
+                if *time > self.start && *time < self.end {
+                    period = gcd(period, time - self.start);
+                }
+            }
+        }
+
+        period.max(span.div_ceil(MAX_PERIODS))
+    }
+
+    pub fn render(&self) -> String {
+        let waveform = self.waveform;
+        let period = self.period.unwrap_or_else(|| self.auto_period()).max(1);
+        let periods = self.end.saturating_sub(self.start).div_ceil(period).max(1);
+
+        let waves: Vec<String> = self
+            .signals
+            .iter()
+            .map(|index| {
+                let signal = &waveform.signals[*index];
+                let radix = self.radixes.get(*index).copied().unwrap_or(self.radix);
+                let is_scalar =
+                    signal.size == 1 && !matches!(signal.var_type, VarType::Real | VarType::String);
+
+                let mut wave = String::new();
+                let mut data = Vec::new();
+                let mut previous = None;
+                for i in 0..periods {
+                    let value = waveform.value_at(signal, self.start + i * period);
+                    if i > 0 && value == previous {
+                        wave.push('.');
+                        continue;
+                    }
+                    previous = value;
+
+                    let bits = value.map(value_bits).unwrap_or_default();
+                    wave.push(match value {
+                        None => 'x',
+                        Some(SignalValue::Scalar(bit)) if is_scalar => bit_char(*bit),
+                        _ if !bits.is_empty() && bits.iter().all(|bit| *bit == Value::X) => 'x',
+                        _ if !bits.is_empty() && bits.iter().all(|bit| *bit == Value::Z) => 'z',
+                        Some(value) => {
+                            data.push(format_value(value, radix));
+                            '='
+                        }
+                    });
+                }
+
+                let mut entry = json!({
+                    "name": waveform.signal_path(signal),
+                    "wave": wave,
+                });
+                if !data.is_empty() {
+                    entry["data"] = json!(data);
+                }
+                format!("  {}", entry)
+            })
+            .collect();
+
+        let foot = json!({
+            "text": format!(
+                "{} per period from {}",
+                format_time(period, waveform.timescale),
+                format_time(self.start, waveform.timescale)
+            )
+        });
+
+        format!(
+            "{{\"signal\": [\n{}\n],\n\"foot\": {}}}\n",
+            waves.join(",\n"),
+            foot
+        )
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn bit_char(bit: Value) -> char {
+    match bit {
+        Value::V0 => '0',
+        Value::V1 => '1',
+        Value::X => 'x',
+        Value::Z => 'z',
+    }
+}
+
+// Build a waveform from a WaveDrom timing diagram, written as JSON or as the
+// JavaScript object literals WaveDrom accepts. Groups become scopes, bus data
+// that reads as numbers becomes vectors and any other data strings
+pub fn parse(text: &str) -> Result<Waveform, Box<dyn Error>> {
+    let json: Json = json5::from_str(text)?;
+    let items = json
+        .get("signal")
+        .and_then(Json::as_array)
+        .ok_or("expected a WaveJSON object with a 'signal' list")?;
+
+    let mut waveform = Waveform::default();
+    for item in items {
+        add_item(&mut waveform, item, None)?;
+    }
+
+    // Every wave ends after its last character
+    let mut timestamps: Vec<u64> = waveform
+        .changes
+        .values()
+        .flatten()
+        .map(|(time, _)| *time)
+        .collect();
+    timestamps.extend(end_times(items));
+    timestamps.sort_unstable();
+    timestamps.dedup();
+    waveform.timestamps = timestamps;
+    waveform.source = Source::Text(text.to_string());
+
+    Ok(waveform)
+}
+
+// Show the diagram of `text` next to `waveform`, under a top level scope named
+// `name`, and return the signals it adds. A period of the diagram lasts `period`
+// timestamps of the waveform, from the start written in its foot or from 0. Without
+// a period, the one written in the foot of diagrams exported by waveplot is used
+pub fn overlay(
+    waveform: &mut Waveform,
+    text: &str,
+    name: &str,
+    period: Option<u64>,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut diagram = parse(text)?;
+    let foot = foot_times(text, waveform.timescale);
+    let period = period
+        .or(foot.map(|(period, _)| period))
+        .ok_or("no period in its foot, give one with --overlay-period")?;
+    let start = foot.map_or(0, |(_, start)| start);
+
+    for changes in diagram.changes.values_mut() {
+        for (time, _) in changes.iter_mut() {
+            *time = start + (*time as u128 * period as u128 / TICKS_PER_PERIOD as u128) as u64;
+        }
+        // Characters shorter than a timestamp leave the last value they hold
+        changes.dedup_by(|later, earlier| {
+            let same = later.0 == earlier.0;
+            if same {
+                std::mem::swap(&mut later.1, &mut earlier.1);
+            }
+            same
+        });
+    }
+
+    let first = waveform.signals.len();
+    waveform.add_overlay(diagram, name);
+    Ok((first..waveform.signals.len()).collect())
+}
+
+// Period and start of a diagram written by WaveJson::render, from its foot
+fn foot_times(text: &str, timescale: Option<(u32, TimescaleUnit)>) -> Option<(u64, u64)> {
+    let json: Json = json5::from_str(text).ok()?;
+    let foot = json.get("foot")?.get("text")?.as_str()?;
+    let (period, start) = foot.split_once(" per period from ")?;
+    Some((
+        parse_time(period, timescale).ok()?,
+        parse_time(start, timescale).ok()?,
+    ))
+}
+
+// Add a signal, or a group of signals under a scope named by its first element.
+// Spacers and anything else without a wave are skipped
+fn add_item(waveform: &mut Waveform, item: &Json, scope: Option<usize>) -> Result<(), String> {
+    match item {
+        Json::Array(items) => {
+            let (name, items) = match items.split_first() {
+                Some((Json::String(name), items)) => (name.clone(), items),
+                _ => (String::new(), items.as_slice()),
+            };
+
+            let index = waveform.scopes.len();
+            waveform.scopes.push(ScopeNode {
+                scope_type: ScopeType::Module,
+                name,
+                parent: scope,
+                children: Vec::new(),
+                signals: Vec::new(),
+            });
+            match scope {
+                Some(parent) => waveform.scopes[parent].children.push(index),
+                None => waveform.top_scopes.push(index),
+            }
+
+            for item in items {
+                add_item(waveform, item, Some(index))?;
+            }
+        }
+        Json::Object(object) => {
+            let Some(wave) = object.get("wave").and_then(Json::as_str) else {
+                return Ok(());
+            };
+            let name = object.get("name").and_then(Json::as_str).unwrap_or("");
+            let data: Vec<String> = match object.get("data") {
+                Some(Json::String(data)) => data.split_whitespace().map(String::from).collect(),
+                Some(Json::Array(data)) => data
+                    .iter()
+                    .map(|value| match value {
+                        Json::String(text) => text.clone(),
+                        value => value.to_string(),
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let period = object.get("period").and_then(Json::as_f64).unwrap_or(1.0);
+
+            let Wave {
+                var_type,
+                size,
+                changes,
+            } = parse_wave(wave, &data, period)
+                .map_err(|err| format!("wave of '{}': {}", name, err))?;
+
+            let code = IdCode::from(waveform.signals.len() as u32);
+            waveform.signals.push(Signal {
+                var_type,
+                size,
+                reference: name.to_string(),
+                index: None,
+                code,
+                scope,
+            });
+            let index = waveform.signals.len() - 1;
+            match scope {
+                Some(scope) => waveform.scopes[scope].signals.push(index),
+                None => waveform.top_signals.push(index),
+            }
+            waveform.changes.insert(code, changes);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// Timestamps per character of a wave stretched by its `period` attribute
+fn ticks_per_character(period: f64) -> u64 {
+    (TICKS_PER_PERIOD as f64 * period).round().max(1.0) as u64
+}
+
+// Time at which the longest wave of a list of items ends
+fn end_times(items: &[Json]) -> Vec<u64> {
+    items
+        .iter()
+        .flat_map(|item| match item {
+            Json::Array(items) => end_times(items),
+            Json::Object(object) => {
+                let length = object
+                    .get("wave")
+                    .and_then(Json::as_str)
+                    .map_or(0, |wave| wave.chars().count()) as u64;
+                let period = object.get("period").and_then(Json::as_f64).unwrap_or(1.0);
+                vec![length * ticks_per_character(period)]
+            }
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+// A signal read from the characters of a wave
+struct Wave {
+    var_type: VarType,
+    size: u32,
+    changes: Vec<(u64, SignalValue)>,
+}
+
+// Type, width and changes of a signal from its wave characters and bus data
+fn parse_wave(wave: &str, data: &[String], period: f64) -> Result<Wave, String> {
+    let ticks = ticks_per_character(period);
+    let is_bus = wave.chars().any(|c| c == '=' || ('2'..='9').contains(&c));
+
+    // Data reading as numbers makes a vector as wide as the widest number
+    let numbers: Option<Vec<Vec<Value>>> =
+        data.iter().map(|text| parse_literal(text).ok()).collect();
+    let (var_type, size) = match (&numbers, is_bus) {
+        (_, false) => (VarType::Wire, 1),
+        (Some(numbers), true) => (
+            VarType::Wire,
+            numbers.iter().map(Vec::len).max().unwrap_or(1).max(1),
+        ),
+        (None, true) => (VarType::String, 1),
+    };
+    let size = size as u32;
+
+    // A level of a scalar, or every bit of a vector set to that level
+    let level = |bit: Value| match (var_type, is_bus) {
+        (VarType::String, _) => SignalValue::String(bit.to_string()),
+        (_, true) => SignalValue::Vector(vec![bit; size as usize].into_iter().collect()),
+        _ => SignalValue::Scalar(bit),
+    };
+
+    let mut changes: Vec<(u64, SignalValue)> = Vec::new();
+    let mut push = |time: u64, value: SignalValue| match changes.last_mut() {
+        Some(last) if last.0 == time => last.1 = value,
+        Some(last) if last.1 == value => {}
+        _ => changes.push((time, value)),
+    };
+
+    let mut next_data = 0;
+    let mut previous = None;
+    for (i, c) in wave.chars().enumerate() {
+        let time = i as u64 * ticks;
+        // '.' and '|' continue the previous character, repeating clock periods
+        let repeated = c == '.' || c == '|';
+        let c = match (repeated, previous) {
+            (false, _) => c,
+            (true, Some(previous)) => previous,
+            (true, None) => continue,
+        };
+        previous = Some(c);
+
+        match c {
+            '0' | 'l' | 'L' | 'd' => push(time, level(Value::V0)),
+            '1' | 'h' | 'H' | 'u' => push(time, level(Value::V1)),
+            'x' => push(time, level(Value::X)),
+            'z' => push(time, level(Value::Z)),
+            'p' | 'P' => {
+                push(time, level(Value::V1));
+                push(time + ticks / 2, level(Value::V0));
+            }
+            'n' | 'N' => {
+                push(time, level(Value::V0));
+                push(time + ticks / 2, level(Value::V1));
+            }
+            // A repeated bus character keeps its value
+            '=' | '2'..='9' if repeated => {}
+            '=' | '2'..='9' => {
+                let value = match (data.get(next_data), &numbers) {
+                    (Some(_), Some(numbers)) => SignalValue::Vector(
+                        resize(&numbers[next_data], size as usize)
+                            .into_iter()
+                            .collect(),
+                    ),
+                    (Some(text), None) => SignalValue::String(text.clone()),
+                    (None, _) => level(Value::X),
+                };
+                next_data += 1;
+                push(time, value);
+            }
+            c => return Err(format!("unknown character '{}'", c)),
+        }
+    }
+
+    Ok(Wave {
+        var_type,
+        size,
+        changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::waveform::{tests::load_fixture, Edge};
+
+    #[test]
+    fn signals_are_exported_one_character_per_period() {
+        let waveform = load_fixture("test6.vcd");
+        let signals = [
+            waveform.find_signal("cpu_tb.clk").unwrap(),
+            waveform.find_signal("dut.pc").unwrap(),
+        ];
+
+        let json = WaveJson::new(&waveform, &signals, 0, 40).render();
+        assert_eq!(
+            json,
+            "{\"signal\": [\n  \
+             {\"name\":\"cpu_tb.clk\",\"wave\":\"01010101\"},\n  \
+             {\"data\":[\"00\",\"01\",\"02\",\"03\"],\"name\":\"cpu_tb.dut.pc\",\"wave\":\"=..=.=.=\"}\n\
+             ],\n\"foot\": {\"text\":\"5 ns per period from 0 ns\"}}\n"
+        );
+    }
+
+    #[test]
+    fn timing_diagrams_are_read_into_signals() {
+        let waveform = parse(
+            "{ signal: [
+                { name: 'clk', wave: 'p...' },
+                ['bus',
+                  { name: 'addr', wave: 'x=.=', data: ['0x10', 12] },
+                  { name: 'state', wave: '=.=.', data: 'IDLE BUSY' },
+                ],
+                {},
+              ] }",
+        )
+        .unwrap();
+
+        assert_eq!(waveform.last_timestamp(), 16);
+        assert_eq!(waveform.scope_path(0), "bus");
+
+        let clk = &waveform.signals[waveform.find_signal("clk").unwrap()];
+        assert_eq!(waveform.next_edge(clk, 0, Edge::Falling), Some(2));
+        assert_eq!(waveform.signal_changes(clk).len(), 8);
+
+        let addr = &waveform.signals[waveform.find_signal("bus.addr").unwrap()];
+        assert_eq!(addr.size, 8);
+        assert_eq!(
+            waveform
+                .value_at(addr, 8)
+                .map(|value| format_value(value, Radix::Hexadecimal)),
+            Some(String::from("10"))
+        );
+        assert_eq!(
+            waveform
+                .value_at(addr, 12)
+                .map(|value| format_value(value, Radix::Unsigned)),
+            Some(String::from("12"))
+        );
+
+        let state = &waveform.signals[waveform.find_signal("state").unwrap()];
+        assert_eq!(state.var_type, VarType::String);
+        assert_eq!(
+            waveform.value_at(state, 9),
+            Some(&SignalValue::String(String::from("BUSY")))
+        );
+
+        assert!(parse("{ signal: [{ name: 'a', wave: '0?1' }] }").is_err());
+    }
+
+    #[test]
+    fn diagrams_are_overlaid_on_a_dump() {
+        let mut waveform = load_fixture("test6.vcd");
+        let diagram = |foot: &str| {
+            format!(
+                "{{ signal: [{{ name: 'clk', wave: 'lh.l' }}, ['bus', {{ name: 'ready', wave: '01' }}]], {} }}",
+                foot
+            )
+        };
+
+        let signals = overlay(
+            &mut waveform,
+            &diagram("foot: { text: '5 ns per period from 10 ns' }"),
+            "expected",
+            None,
+        )
+        .unwrap();
+        assert_eq!(signals.len(), 2);
+        assert_eq!(
+            waveform.signal_path(&waveform.signals[signals[1]]),
+            "expected.bus.ready"
+        );
+        let clk = &waveform.signals[waveform.find_signal("expected.clk").unwrap()];
+        let times: Vec<u64> = waveform
+            .signal_changes(clk)
+            .iter()
+            .map(|(time, _)| *time)
+            .collect();
+        assert_eq!(times, vec![10, 15, 25]);
+        assert_eq!(waveform.last_timestamp(), 40);
+
+        // Hand written diagrams need a period, which may be shorter than a character
+        assert!(overlay(&mut waveform, &diagram("foot: {}"), "again", None).is_err());
+        let clock = "{ signal: [{ name: 'clk', wave: 'p...' }] }";
+        let signals = overlay(&mut waveform, clock, "clock", Some(1)).unwrap();
+        let clk = &waveform.signals[signals[0]];
+        assert_eq!(waveform.signal_changes(clk).len(), 4);
+        assert_eq!(
+            waveform.value_at(clk, 2),
+            Some(&SignalValue::Scalar(Value::V0))
+        );
+    }
+}